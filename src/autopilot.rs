@@ -0,0 +1,197 @@
+use crate::{
+    input::Controls,
+    powerup::PowerUpKind,
+    state::{Ball, State},
+};
+
+/// Upper bound on wall bounces followed when predicting where a ball lands.
+const MAX_PREDICTED_BOUNCES: usize = 16;
+/// Ticks without scoring before aiming at a different brick, to break out of
+/// loops around bricks that can't be broken.
+const RETARGET_TICKS: u32 = 20 * 60;
+
+/// Plays the game by itself. Call [`AutoPilot::update`] with the current
+/// state each tick, then hand it to `MovementSystem::input` in place of a
+/// [`Controller`](crate::input::Controller).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AutoPilot {
+    dir: f32,
+    fire: bool,
+    last_total: u32,
+    idle_ticks: u32,
+}
+
+impl AutoPilot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decides what to do this tick. The paddle heads for wherever the most
+    /// urgent ball will come down, offset so the bounce sends it towards the
+    /// lowest remaining brick.
+    pub fn update(&mut self, state: &State) {
+        if state.score.total == self.last_total {
+            self.idle_ticks += 1;
+        } else {
+            self.last_total = state.score.total;
+            self.idle_ticks = 0;
+        }
+
+        let paddle = &state.player.body;
+        let paddle_top = paddle.pos.y + paddle.size.y;
+
+        let target_x = state
+            .balls
+            .iter()
+            .filter(|b| b.fired && b.vel.y < 0.0)
+            .filter_map(|b| predict_landing(b, paddle_top, state.arena_size.x).map(|x| (b, x)))
+            .min_by(|a, b| a.0.body.pos.y.total_cmp(&b.0.body.pos.y))
+            .map(|(ball, land_x)| self.aim(state, ball, land_x))
+            // Nothing falling, so wait under the middle of the bricks.
+            .unwrap_or_else(|| match self.target_brick(state) {
+                Some(x) => x - paddle.size.x * 0.5,
+                None => (state.arena_size.x - paddle.size.x) * 0.5,
+            });
+
+        // Ease off when close so the paddle doesn't jitter around the target.
+        self.dir = ((target_x - paddle.pos.x) * 0.5).clamp(-1.0, 1.0);
+
+        let waiting = state.balls.iter().any(|b| !b.fired);
+        let lasers = state.has_effect(PowerUpKind::Laser);
+        // Fire only registers on a fresh press, so alternate to keep pressing.
+        self.fire = (waiting || lasers) && !self.fire;
+    }
+
+    /// Paddle x that makes `ball`, landing at `land_x`, bounce towards the
+    /// target brick. Mirrors the angle bounce in `MovementSystem`.
+    fn aim(&self, state: &State, ball: &Ball, land_x: f32) -> f32 {
+        let paddle = &state.player.body;
+        let span = paddle.size.x - ball.body.size.x;
+        let ball_x = land_x - ball.body.size.x * 0.5;
+        let rel = match self.target_brick(state) {
+            Some(x) => {
+                let paddle_top = paddle.pos.y + paddle.size.y;
+                let rise = (state.arena_size.y - paddle_top).max(1.0);
+                // The bounce leaves with a y speed of 2 and an x speed
+                // between -1 and 1 depending on where the ball hits.
+                let want = ((x - land_x) / rise * 2.0).clamp(-0.9, 0.9);
+                (want + 1.0) * 0.5 * span
+            }
+            None => span * 0.5,
+        };
+        ball_x - rel
+    }
+
+    /// Center x of the brick to aim for. Normally the lowest one still to be
+    /// cleared, moving on to higher ones the longer nothing gets hit.
+    fn target_brick(&self, state: &State) -> Option<f32> {
+        let mut targets: Vec<_> = state.bricks.iter().filter(|b| b.counts_toward_win()).collect();
+        targets.sort_by(|a, b| a.body.pos.y.total_cmp(&b.body.pos.y));
+        let skip = (self.idle_ticks / RETARGET_TICKS) as usize;
+        targets
+            .get(skip % targets.len().max(1))
+            .map(|b| b.body.pos.x + b.body.size.x * 0.5)
+    }
+}
+
+impl Controls for AutoPilot {
+    fn dir(&self) -> f32 {
+        self.dir
+    }
+
+    fn fire_just_pressed(&self) -> bool {
+        self.fire
+    }
+}
+
+/// Where the center of `ball` will be when its bottom reaches `floor`,
+/// following bounces off the side walls. Bricks are ignored, and a rising
+/// ball bounces off the ceiling.
+pub fn predict_landing(ball: &Ball, floor: f32, arena_width: f32) -> Option<f32> {
+    let size = ball.body.size;
+    let mut pos = ball.body.pos;
+    let mut vel = ball.vel;
+    if vel.y == 0.0 {
+        return None;
+    }
+    if vel.y > 0.0 {
+        // Rough guess for a rising ball: it comes back down from the top.
+        vel.y = -vel.y;
+    }
+
+    let max_x = arena_width - size.x;
+    for _ in 0..MAX_PREDICTED_BOUNCES {
+        let t_floor = (floor - pos.y) / vel.y;
+        let t_wall = if vel.x > 0.0 {
+            (max_x - pos.x) / vel.x
+        } else if vel.x < 0.0 {
+            -pos.x / vel.x
+        } else {
+            f32::INFINITY
+        };
+        if t_floor <= t_wall {
+            return Some(pos.x + vel.x * t_floor.max(0.0) + size.x * 0.5);
+        }
+        pos += vel * t_wall;
+        vel.x = -vel.x;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        level::Level,
+        system::{Message, MovementSystem, PADDLE_SPEED},
+        timestep::SIM_STEP,
+    };
+
+    #[test]
+    fn prediction_follows_wall_bounces() {
+        let state = State::new(
+            glam::vec2(80.0, 74.0),
+            glam::vec2(16.0, 3.0),
+            glam::vec2(4.0, 4.0),
+            glam::vec2(8.0, 4.0),
+        );
+        let mut ball = state.new_ball();
+        ball.body.pos = glam::vec2(66.0, 44.0);
+        ball.vel = glam::vec2(10.0, -10.0);
+        // Reaches the right wall 10 units down, then travels back 30.
+        let landed = predict_landing(&ball, 4.0, 80.0).unwrap();
+        assert!((landed - 48.0).abs() < 1e-4, "{}", landed);
+    }
+
+    #[test]
+    fn autopilot_keeps_the_ball_in_play() {
+        let level = Level::from_ron(
+            r#"Level(
+                name: "Test",
+                drop_rate: 0.0,
+                legend: { '1': (hits: 1) },
+                grid: ["1111111111"],
+            )"#,
+        )
+        .unwrap();
+        let mut state = State::new(
+            glam::vec2(80.0, 74.0),
+            glam::vec2(16.0, 3.0),
+            glam::vec2(4.0, 4.0),
+            glam::vec2(8.0, 4.0),
+        );
+        state.new_game();
+        state.load_level(&level).unwrap();
+
+        let mut pilot = AutoPilot::new();
+        let mut movement = MovementSystem::new(PADDLE_SPEED);
+        let mut messages = Vec::new();
+        for _ in 0..60 * 60 {
+            pilot.update(&state);
+            movement.input(&pilot);
+            movement.update(&mut state, SIM_STEP, &mut messages);
+        }
+        assert!(!messages.iter().any(|m| matches!(m, Message::Drop)));
+        assert!(messages.iter().any(|m| matches!(m, Message::Scored { .. })));
+    }
+}
@@ -0,0 +1,21 @@
+//! Runs the reinforcement learning environment over stdin and stdout using
+//! the line-delimited JSON protocol described in `brick_breaker::env::serve`.
+//!
+//! Usage: `env_server [level file]`
+
+use brick_breaker::{
+    env::{self, Env},
+    level::Level,
+};
+
+fn main() -> anyhow::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "./assets/levels/01.ron".to_string());
+    let mut env = Env::new(Level::load(&path)?)?;
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    env::serve(&mut env, stdin.lock(), stdout.lock())?;
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! Plays back a replay recorded by the game without opening a window and
+//! checks that it ends up in the same state.
+//!
+//! Usage: `replay <file> [campaign manifest]`
+
+use brick_breaker::{campaign::Campaign, replay::Replay};
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("usage: replay <file> [campaign manifest]"))?;
+    let manifest = args
+        .next()
+        .unwrap_or_else(|| "./assets/levels/campaign.ron".to_string());
+
+    let replay = Replay::load(&path)?;
+    let campaign = Campaign::load(&manifest)?;
+    let state = replay.play(&campaign)?;
+
+    println!(
+        "seed {} level {}: {} ticks, score {}, {} lives, {} bricks left",
+        replay.seed,
+        replay.level + 1,
+        replay.ticks,
+        state.score.total,
+        state.lives,
+        state.bricks.len(),
+    );
+    if replay.matches(&state) {
+        println!("final state matches the recording");
+        Ok(())
+    } else {
+        anyhow::bail!("final state differs from the recording")
+    }
+}
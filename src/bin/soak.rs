@@ -0,0 +1,145 @@
+//! Plays thousands of games with the autopilot, headless, looking for physics
+//! bugs: balls escaping the arena, balls that get stuck, and balls sinking
+//! into bricks. Every failing game is saved as a replay for debugging.
+//!
+//! Usage: `soak [games] [max ticks per game] [campaign manifest]`
+
+use brick_breaker::{
+    autopilot::AutoPilot,
+    campaign::Campaign,
+    env::{ARENA_SIZE, BALL_SIZE, BRICK_SIZE, PLAYER_SIZE},
+    replay::Replay,
+    state::{Body, State},
+    system::{Message, MovementSystem, PADDLE_SPEED},
+    timestep::SIM_STEP,
+};
+
+/// Ticks a fired ball can go without touching anything before it counts as
+/// stuck. Crossing the arena diagonally at the slowest ball speed takes well
+/// under this.
+const STUCK_TICKS: u64 = 60 * 60;
+/// How far a ball may overlap something before it counts as a bug.
+const TOLERANCE: f32 = 1e-3;
+/// Default tick limit per game: an hour of play. The autopilot is slow and
+/// careful, so it can take half an hour to clear a level.
+const MAX_TICKS: u64 = 60 * 60 * 60;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let games: u64 = args.next().map(|a| a.parse()).transpose()?.unwrap_or(1000);
+    let max_ticks: u64 = args.next().map(|a| a.parse()).transpose()?.unwrap_or(MAX_TICKS);
+    let manifest = args
+        .next()
+        .unwrap_or_else(|| "./assets/levels/campaign.ron".to_string());
+    let campaign = Campaign::load(&manifest)?;
+
+    let mut failures = 0;
+    let mut wins = 0;
+    let mut timeouts = 0;
+    for seed in 0..games {
+        let level = seed as usize % campaign.len();
+        match play(&campaign, seed, level, max_ticks)? {
+            Outcome::Won => wins += 1,
+            Outcome::Lost => (),
+            Outcome::TimedOut => timeouts += 1,
+            Outcome::Failed { tick, reason, replay } => {
+                failures += 1;
+                let path = format!("soak-{}.replay", seed);
+                println!("seed {} level {} tick {}: {} (saved {})", seed, level + 1, tick, reason, path);
+                replay.save(&path)?;
+            }
+        }
+    }
+
+    println!(
+        "{} games, {} won, {} timed out, {} failed",
+        games, wins, timeouts, failures
+    );
+    if failures > 0 {
+        anyhow::bail!("{} games hit physics bugs", failures);
+    }
+    Ok(())
+}
+
+enum Outcome {
+    Won,
+    Lost,
+    /// Still going after the tick limit. Slow, but not a bug.
+    TimedOut,
+    Failed { tick: u64, reason: String, replay: Replay },
+}
+
+fn play(campaign: &Campaign, seed: u64, level: usize, max_ticks: u64) -> anyhow::Result<Outcome> {
+    let mut state = State::new(ARENA_SIZE, PLAYER_SIZE, BALL_SIZE, BRICK_SIZE);
+    state.new_game();
+    state.reseed(seed);
+    state.load_level(&campaign.levels[level])?;
+    let mut replay = Replay::new(seed, level, &state);
+
+    let mut pilot = AutoPilot::new();
+    let mut movement = MovementSystem::new(PADDLE_SPEED);
+    let mut messages = Vec::new();
+    let mut last_bounce = 0;
+    for tick in 0..max_ticks {
+        pilot.update(&state);
+        movement.input(&pilot);
+        replay.record(movement.last_input());
+        movement.update(&mut state, SIM_STEP, &mut messages);
+
+        let mut outcome = None;
+        for msg in messages.drain(..) {
+            match msg {
+                Message::Bounce { .. } | Message::Drop => last_bounce = tick,
+                Message::Win => outcome = Some(Outcome::Won),
+                Message::GameOver => outcome = Some(Outcome::Lost),
+                _ => (),
+            }
+        }
+
+        if !state.balls.iter().any(|b| b.fired) {
+            last_bounce = tick;
+        }
+        let reason = check(&state).or_else(|| {
+            (tick - last_bounce > STUCK_TICKS).then(|| format!("ball touched nothing for {} ticks", STUCK_TICKS))
+        });
+        if let Some(reason) = reason {
+            replay.finish(&state);
+            return Ok(Outcome::Failed { tick, reason, replay });
+        }
+        if let Some(outcome) = outcome {
+            return Ok(outcome);
+        }
+    }
+
+    Ok(Outcome::TimedOut)
+}
+
+/// Looks for anything a ball should never be able to do.
+fn check(state: &State) -> Option<String> {
+    for (i, ball) in state.balls.iter().enumerate() {
+        let body = &ball.body;
+        if !body.pos.is_finite() || !ball.vel.is_finite() {
+            return Some(format!("ball {} has a non-finite position or velocity", i));
+        }
+        if body.pos.x < -TOLERANCE
+            || body.pos.x + body.size.x > state.arena_size.x + TOLERANCE
+            || body.pos.y + body.size.y > state.arena_size.y + TOLERANCE
+        {
+            return Some(format!("ball {} left the arena at {}", i, body.pos));
+        }
+        if ball.fired && ball.vel.y.abs() < 1e-3 {
+            return Some(format!("ball {} is stuck moving sideways", i));
+        }
+        if let Some(brick) = state.bricks.iter().find(|b| overlap(body, &b.body)) {
+            return Some(format!("ball {} at {} is inside the brick at {}", i, body.pos, brick.body.pos));
+        }
+    }
+    None
+}
+
+fn overlap(a: &Body, b: &Body) -> bool {
+    a.pos.x + TOLERANCE < b.pos.x + b.size.x
+        && a.pos.x + a.size.x > b.pos.x + TOLERANCE
+        && a.pos.y + TOLERANCE < b.pos.y + b.size.y
+        && a.pos.y + a.size.y > b.pos.y + TOLERANCE
+}
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::input::Button;
+
+/// A physical input that can be bound to a [`Button`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    /// A keyboard key, by its winit `VirtualKeyCode` name, e.g. `"Space"`.
+    Key(String),
+    /// A mouse button, by its raw device id.
+    Mouse(u32),
+    Gamepad(GamepadButton),
+}
+
+impl Binding {
+    #[cfg(feature = "game")]
+    pub fn key(key: winit::event::VirtualKeyCode) -> Self {
+        match serde_json::to_value(key) {
+            Ok(serde_json::Value::String(name)) => Self::Key(name),
+            _ => unreachable!("key codes serialize as their names"),
+        }
+    }
+
+    /// Short name for showing in menus.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Key(name) => name.to_uppercase(),
+            Self::Mouse(id) => format!("MOUSE{}", id),
+            Self::Gamepad(button) => format!("PAD {:?}", button).to_uppercase(),
+        }
+    }
+}
+
+/// Gamepad buttons, named by position so they mean the same thing across
+/// pad layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    Start,
+    Select,
+    LeftShoulder,
+    RightShoulder,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum BindingsError {
+    #[error("nothing is bound to {0:?}")]
+    Unbound(Button),
+    #[error("{0:?} is bound to more than one action")]
+    Conflict(Binding),
+    #[error("no such key: {0}")]
+    UnknownKey(String),
+}
+
+/// Which physical inputs press each [`Button`]. Several can share a button,
+/// but each input can only press one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bindings {
+    pub left: Vec<Binding>,
+    pub right: Vec<Binding>,
+    pub up: Vec<Binding>,
+    pub down: Vec<Binding>,
+    pub fire: Vec<Binding>,
+    pub back: Vec<Binding>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        fn keys(names: &[&str]) -> Vec<Binding> {
+            names.iter().map(|n| Binding::Key(n.to_string())).collect()
+        }
+        let with = |mut bindings: Vec<Binding>, extra: &[Binding]| {
+            bindings.extend_from_slice(extra);
+            bindings
+        };
+        Self {
+            left: with(keys(&["A", "Left"]), &[Binding::Gamepad(GamepadButton::DPadLeft)]),
+            right: with(keys(&["D", "Right"]), &[Binding::Gamepad(GamepadButton::DPadRight)]),
+            up: with(keys(&["W", "Up"]), &[Binding::Gamepad(GamepadButton::DPadUp)]),
+            down: with(keys(&["S", "Down"]), &[Binding::Gamepad(GamepadButton::DPadDown)]),
+            fire: with(
+                keys(&["Space", "Return"]),
+                &[Binding::Mouse(0), Binding::Gamepad(GamepadButton::South)],
+            ),
+            back: with(keys(&["Escape"]), &[Binding::Gamepad(GamepadButton::Start)]),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, button: Button) -> &[Binding] {
+        match button {
+            Button::Left => &self.left,
+            Button::Right => &self.right,
+            Button::Up => &self.up,
+            Button::Down => &self.down,
+            Button::Fire => &self.fire,
+            Button::Back => &self.back,
+        }
+    }
+
+    fn get_mut(&mut self, button: Button) -> &mut Vec<Binding> {
+        match button {
+            Button::Left => &mut self.left,
+            Button::Right => &mut self.right,
+            Button::Up => &mut self.up,
+            Button::Down => &mut self.down,
+            Button::Fire => &mut self.fire,
+            Button::Back => &mut self.back,
+        }
+    }
+
+    /// The button `binding` presses, if any.
+    pub fn button(&self, binding: &Binding) -> Option<Button> {
+        Button::ALL.into_iter().find(|&b| self.get(b).contains(binding))
+    }
+
+    /// Makes `key` the only key for `button`, keeping its mouse and gamepad
+    /// bindings. Whichever button had `key` before gets `button`'s old keys
+    /// instead, so nothing is left unbound.
+    pub fn rebind_key(&mut self, button: Button, key: Binding) {
+        let (old_keys, others): (Vec<_>, Vec<_>) = self
+            .get_mut(button)
+            .drain(..)
+            .partition(|b| matches!(b, Binding::Key(_)));
+        *self.get_mut(button) = others;
+
+        if let Some(previous) = self.button(&key) {
+            let bindings = self.get_mut(previous);
+            bindings.retain(|b| *b != key);
+            bindings.extend(old_keys.into_iter().filter(|b| *b != key));
+        }
+        self.get_mut(button).insert(0, key);
+    }
+
+    /// Checks every button can be pressed, no input presses two buttons,
+    /// and every key exists.
+    pub fn validate(&self) -> Result<(), BindingsError> {
+        let mut seen = std::collections::HashSet::new();
+        for button in Button::ALL {
+            let bindings = self.get(button);
+            if bindings.is_empty() {
+                return Err(BindingsError::Unbound(button));
+            }
+            for binding in bindings {
+                if !seen.insert(binding) {
+                    return Err(BindingsError::Conflict(binding.clone()));
+                }
+                #[cfg(feature = "game")]
+                if let Binding::Key(name) = binding {
+                    let value = serde_json::Value::String(name.clone());
+                    if serde_json::from_value::<winit::event::VirtualKeyCode>(value).is_err() {
+                        return Err(BindingsError::UnknownKey(name.clone()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// For `#[serde(deserialize_with)]`. Falls back to the defaults, rather
+    /// than failing, when the saved bindings are malformed or invalid.
+    pub fn deserialize_or_default<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let bindings = serde_json::from_value::<Self>(value)
+            .map_err(|e| e.to_string())
+            .and_then(|b| b.validate().map(|()| b).map_err(|e| e.to_string()));
+        Ok(bindings.unwrap_or_else(|e| {
+            log::warn!("Resetting controls to the defaults: {}", e);
+            Self::default()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> Binding {
+        Binding::Key(name.to_string())
+    }
+
+    #[test]
+    fn rebinding_a_taken_key_swaps_it() {
+        let mut bindings = Bindings::default();
+        bindings.rebind_key(Button::Left, key("D"));
+
+        assert_eq!(bindings.button(&key("D")), Some(Button::Left));
+        assert_eq!(bindings.button(&key("A")), Some(Button::Right));
+        assert_eq!(bindings.button(&key("Left")), Some(Button::Right));
+        // Non-key bindings stay where they were.
+        assert_eq!(
+            bindings.button(&Binding::Gamepad(GamepadButton::DPadLeft)),
+            Some(Button::Left)
+        );
+        assert_eq!(bindings.validate(), Ok(()));
+    }
+
+    #[test]
+    fn invalid_bindings_reset_to_defaults() {
+        #[derive(Deserialize)]
+        struct Settings {
+            #[serde(deserialize_with = "Bindings::deserialize_or_default")]
+            bindings: Bindings,
+        }
+
+        let mut conflicting = Bindings::default();
+        conflicting.fire.push(key("A"));
+        let json = format!(r#"{{"bindings": {}}}"#, serde_json::to_string(&conflicting).unwrap());
+        let settings: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(settings.bindings, Bindings::default());
+
+        let settings: Settings = serde_json::from_str(r#"{"bindings": {"left": 3}}"#).unwrap();
+        assert_eq!(settings.bindings, Bindings::default());
+    }
+}
@@ -0,0 +1,151 @@
+use std::{io::Write, path::Path};
+
+use crate::level::{Level, LevelError};
+
+/// The ordered list of levels making up the game, as described by
+/// `assets/levels/campaign.ron`.
+pub struct Campaign {
+    pub levels: Vec<Level>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename = "Campaign")]
+struct Manifest {
+    /// Level files, relative to the manifest.
+    levels: Vec<String>,
+}
+
+impl Campaign {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).map_err(|source| LevelError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        let manifest: Manifest = ron::from_str(&data)?;
+        if manifest.levels.is_empty() {
+            return Err(LevelError::EmptyCampaign);
+        }
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let levels = manifest
+            .levels
+            .iter()
+            .map(|file| Level::load(dir.join(file)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { levels })
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Always false for a loaded campaign, since loading rejects empty ones.
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+}
+
+/// How far the player has got through the campaign. Saved next to
+/// `settings.json` so it survives restarts.
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct Progress {
+    /// Index of the furthest level the player has reached.
+    pub highest_unlocked: usize,
+}
+
+impl Progress {
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_default(),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        let contents = serde_json::to_string_pretty(self)?;
+        write!(&mut file, "{}", contents)?;
+        Ok(())
+    }
+
+    /// Records that `level` has been reached. Returns whether it's new.
+    pub fn unlock(&mut self, level: usize) -> bool {
+        if level > self.highest_unlocked {
+            self.highest_unlocked = level;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory for files a test writes, emptied first.
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("brick_breaker_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn loads_the_bundled_campaign_in_order() {
+        let campaign = Campaign::load("./assets/levels/campaign.ron").unwrap();
+        assert_eq!(campaign.len(), 3);
+        assert_eq!(campaign.levels[0].name, "Opening");
+    }
+
+    #[test]
+    fn bad_manifests_are_rejected() {
+        let dir = scratch_dir("campaign");
+        let load = |manifest: &str| {
+            let path = dir.join("campaign.ron");
+            std::fs::write(&path, manifest).unwrap();
+            Campaign::load(path)
+        };
+
+        assert!(matches!(load("Campaign(levels: [])"), Err(LevelError::EmptyCampaign)));
+        assert!(matches!(load("Campaign(lvls: [])"), Err(LevelError::Parse(_))));
+        match load(r#"Campaign(levels: ["missing.ron"])"#) {
+            Err(LevelError::Io { path, .. }) => assert!(path.ends_with("missing.ron"), "{}", path),
+            other => panic!("expected an error for the missing level, got {:?}", other.map(|c| c.len())),
+        }
+        std::fs::write(dir.join("broken.ron"), "Level(").unwrap();
+        match load(r#"Campaign(levels: ["broken.ron"])"#) {
+            Err(LevelError::InFile { path, source }) => {
+                assert!(path.ends_with("broken.ron"), "{}", path);
+                assert!(matches!(*source, LevelError::Parse(_)));
+            }
+            other => panic!("expected an error for the broken level, got {:?}", other.map(|c| c.len())),
+        }
+        assert!(matches!(Campaign::load(dir.join("nowhere.ron")), Err(LevelError::Io { .. })));
+    }
+
+    #[test]
+    fn unlock_only_moves_forward() {
+        let mut progress = Progress::default();
+        assert!(progress.unlock(2));
+        assert!(!progress.unlock(2));
+        assert!(!progress.unlock(1));
+        assert_eq!(progress.highest_unlocked, 2);
+    }
+
+    #[test]
+    fn progress_survives_a_round_trip() {
+        let dir = scratch_dir("progress");
+        let path = dir.join("progress.json");
+        assert_eq!(Progress::load(&path).highest_unlocked, 0);
+
+        Progress { highest_unlocked: 4 }.save(&path).unwrap();
+        assert_eq!(Progress::load(&path).highest_unlocked, 4);
+
+        // A damaged file starts over rather than failing.
+        std::fs::write(&path, "{ not json").unwrap();
+        assert_eq!(Progress::load(&path).highest_unlocked, 0);
+    }
+}
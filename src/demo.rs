@@ -0,0 +1,87 @@
+use brick_breaker::{
+    autopilot::AutoPilot,
+    campaign::Campaign,
+    state::State,
+    system::{Message, MovementSystem, PADDLE_SPEED},
+};
+
+/// Seconds the main menu has to sit untouched before the demo starts.
+pub const ATTRACT_DELAY: f32 = 15.0;
+
+/// Attract mode: the autopilot plays through the campaign behind the main
+/// menu once the player has left it alone for a while.
+pub struct Demo {
+    state: State,
+    pilot: AutoPilot,
+    movement: MovementSystem,
+    messages: Vec<Message>,
+    level: usize,
+    idle: f32,
+    running: bool,
+}
+
+impl Demo {
+    /// `state` is a fresh state with the same arena and body sizes as the
+    /// real game.
+    pub fn new(state: State) -> Self {
+        Self {
+            state,
+            pilot: AutoPilot::new(),
+            movement: MovementSystem::new(PADDLE_SPEED),
+            messages: Vec::new(),
+            level: 0,
+            idle: 0.0,
+            running: false,
+        }
+    }
+
+    /// The player did something, so stop the demo and restart the countdown.
+    pub fn interrupt(&mut self) {
+        self.idle = 0.0;
+        self.running = false;
+    }
+
+    /// The demo game, if one is being played.
+    pub fn state(&self) -> Option<&State> {
+        self.running.then_some(&self.state)
+    }
+
+    /// Runs `ticks` simulation steps of length `step`, starting the demo once
+    /// the menu has been idle long enough.
+    pub fn update(&mut self, campaign: &Campaign, ticks: u32, step: f32) {
+        if !self.running {
+            self.idle += ticks as f32 * step;
+            if self.idle < ATTRACT_DELAY {
+                return;
+            }
+            self.running = true;
+            self.state.new_game();
+            self.state.reseed(rand::random());
+            self.load(campaign);
+        }
+
+        for _ in 0..ticks {
+            self.pilot.update(&self.state);
+            self.movement.input(&self.pilot);
+            self.movement.update(&mut self.state, step, &mut self.messages);
+            let won = self.messages.iter().any(|m| matches!(m, Message::Win));
+            let lost = self.messages.iter().any(|m| matches!(m, Message::GameOver));
+            self.messages.clear();
+            if lost {
+                self.state.new_game();
+                self.load(campaign);
+            } else if won {
+                self.level = (self.level + 1) % campaign.len();
+                self.load(campaign);
+            }
+        }
+    }
+
+    fn load(&mut self, campaign: &Campaign) {
+        let level = &campaign.levels[self.level];
+        if let Err(e) = self.state.load_level(level) {
+            log::error!("Couldn't load level \"{}\" for the demo: {}", level.name, e);
+            self.interrupt();
+        }
+    }
+}
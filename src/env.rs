@@ -0,0 +1,264 @@
+use std::io::{BufRead, Write};
+
+use crate::{
+    input::TickInput,
+    level::{Level, LevelError, MAX_BRICK_HITS},
+    state::State,
+    system::{Message, MovementSystem, PADDLE_SPEED},
+    timestep::SIM_STEP,
+};
+
+/// Body sizes matching the sprites the game uses, so agents trained here see
+/// the same physics as players.
+pub const ARENA_SIZE: glam::Vec2 = glam::vec2(80.0, 74.0);
+pub const PLAYER_SIZE: glam::Vec2 = glam::vec2(16.0, 3.0);
+pub const BALL_SIZE: glam::Vec2 = glam::vec2(4.0, 4.0);
+pub const BRICK_SIZE: glam::Vec2 = glam::vec2(8.0, 4.0);
+
+/// Reward taken away each time the agent loses a life.
+pub const LIFE_PENALTY: f32 = 50.0;
+
+/// The paddle direction and fire button for one step.
+pub type Action = TickInput;
+
+/// Gym-style wrapper that plays a single level headless, as fast as the CPU
+/// allows.
+///
+/// Observations are laid out as paddle x, ball x and y, ball velocity x and
+/// y, then one cell per brick slot in row-major order from the top left.
+/// Positions are divided by the arena size, velocities by the level's ball
+/// speed, and brick cells hold the remaining hits over [`MAX_BRICK_HITS`]
+/// (zero when empty). The ball reported is the lowest one in play.
+pub struct Env {
+    level: Level,
+    state: State,
+    movement: MovementSystem,
+    messages: Vec<Message>,
+    cols: usize,
+    rows: usize,
+}
+
+impl Env {
+    pub fn new(level: Level) -> Result<Self, LevelError> {
+        let mut state = State::new(ARENA_SIZE, PLAYER_SIZE, BALL_SIZE, BRICK_SIZE);
+        state.load_level(&level)?;
+        Ok(Self {
+            level,
+            state,
+            movement: MovementSystem::new(PADDLE_SPEED),
+            messages: Vec::new(),
+            cols: (ARENA_SIZE.x / BRICK_SIZE.x) as usize,
+            rows: (ARENA_SIZE.y * 0.5 / BRICK_SIZE.y) as usize,
+        })
+    }
+
+    /// Length of every observation this environment returns.
+    pub fn observation_len(&self) -> usize {
+        5 + self.cols * self.rows
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Starts a new game on the level and returns the first observation.
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.state.new_game();
+        self.state.reseed(seed);
+        self.state
+            .load_level(&self.level)
+            .expect("level was validated when the environment was created");
+        self.observe()
+    }
+
+    /// Advances the simulation one tick. Reward is the points scored, less
+    /// [`LIFE_PENALTY`] for a lost life. The episode is done once the level
+    /// is cleared or the game is over. As in the game, fire is ignored on
+    /// the first step after a reset.
+    pub fn step(&mut self, action: Action) -> (Vec<f32>, f32, bool) {
+        let before = self.state.score.total;
+        self.movement.input(&action);
+        self.movement.update(&mut self.state, SIM_STEP, &mut self.messages);
+
+        let mut reward = (self.state.score.total - before) as f32;
+        let mut done = false;
+        for msg in self.messages.drain(..) {
+            match msg {
+                Message::LifeLost(_) => reward -= LIFE_PENALTY,
+                Message::Win | Message::GameOver => done = true,
+                _ => (),
+            }
+        }
+        (self.observe(), reward, done)
+    }
+
+    fn observe(&self) -> Vec<f32> {
+        let state = &self.state;
+        let mut obs = Vec::with_capacity(self.observation_len());
+
+        let paddle = &state.player.body;
+        obs.push((paddle.pos.x + paddle.size.x * 0.5) / ARENA_SIZE.x);
+        let ball = state
+            .balls
+            .iter()
+            .min_by(|a, b| a.body.pos.y.total_cmp(&b.body.pos.y));
+        match ball {
+            Some(ball) => {
+                let center = ball.body.pos + ball.body.size * 0.5;
+                obs.extend_from_slice(&[
+                    center.x / ARENA_SIZE.x,
+                    center.y / ARENA_SIZE.y,
+                    ball.vel.x / state.ball_speed,
+                    ball.vel.y / state.ball_speed,
+                ]);
+            }
+            None => obs.extend_from_slice(&[0.0; 4]),
+        }
+
+        let grid_start = obs.len();
+        obs.resize(self.observation_len(), 0.0);
+        for brick in &state.bricks {
+            let center = brick.body.pos + brick.body.size * 0.5;
+            let col = (center.x / BRICK_SIZE.x) as usize;
+            let row = ((ARENA_SIZE.y - center.y) / BRICK_SIZE.y) as usize;
+            if col < self.cols && row < self.rows {
+                obs[grid_start + row * self.cols + col] = brick.status as f32 / MAX_BRICK_HITS as f32;
+            }
+        }
+        obs
+    }
+}
+
+/// One line of the JSON protocol read by [`serve`].
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Request {
+    Reset { seed: u64 },
+    Step { dir: f32, fire: bool },
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Response {
+    observation: Vec<f32>,
+    reward: f32,
+    done: bool,
+}
+
+/// Drives `env` with line-delimited JSON, so training scripts in any
+/// language can use it. Each line of `input` is either
+/// `{"reset": {"seed": 1}}` or `{"step": {"dir": -1.0, "fire": false}}`,
+/// and gets one line back of the form
+/// `{"observation": [...], "reward": 0.0, "done": false}`. Malformed lines
+/// are answered with `{"error": "..."}`.
+pub fn serve(env: &mut Env, input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str(&line) {
+            Ok(Request::Reset { seed }) => Response {
+                observation: env.reset(seed),
+                reward: 0.0,
+                done: false,
+            },
+            Ok(Request::Step { dir, fire }) => {
+                let (observation, reward, done) = env.step(Action {
+                    dir: dir.clamp(-1.0, 1.0),
+                    fire,
+                });
+                Response {
+                    observation,
+                    reward,
+                    done,
+                }
+            }
+            Err(e) => {
+                writeln!(output, "{}", serde_json::json!({ "error": e.to_string() }))?;
+                output.flush()?;
+                continue;
+            }
+        };
+        serde_json::to_writer(&mut output, &response)?;
+        writeln!(output)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env() -> Env {
+        let level = Level::from_ron(
+            r#"Level(
+                name: "Test",
+                legend: { '1': (hits: 1), '4': (hits: 4) },
+                grid: ["4111111114", "1111111111"],
+            )"#,
+        )
+        .unwrap();
+        Env::new(level).unwrap()
+    }
+
+    fn run(env: &mut Env, seed: u64) -> (Vec<f32>, f32) {
+        env.reset(seed);
+        let mut total = 0.0;
+        let mut obs = Vec::new();
+        for tick in 0..2000 {
+            let action = Action {
+                dir: if tick % 120 < 60 { 1.0 } else { -1.0 },
+                fire: tick % 30 == 1,
+            };
+            let (o, reward, done) = env.step(action);
+            obs = o;
+            total += reward;
+            if done {
+                break;
+            }
+        }
+        (obs, total)
+    }
+
+    #[test]
+    fn observation_describes_the_level() {
+        let mut env = env();
+        let obs = env.reset(0);
+        assert_eq!(obs.len(), env.observation_len());
+        assert_eq!(obs[0], 0.5);
+        // Top row starts with a four-hit brick.
+        assert_eq!(obs[5], 1.0);
+        assert_eq!(obs[6], 0.25);
+        assert_eq!(obs[5 + 2 * env.cols], 0.0);
+    }
+
+    #[test]
+    fn same_seed_same_episode() {
+        let mut env = env();
+        let first = run(&mut env, 3);
+        let second = run(&mut env, 3);
+        assert_eq!(first, second);
+        assert!(first.0.len() == env.observation_len());
+    }
+
+    #[test]
+    fn serves_json_lines() {
+        let mut env = env();
+        let input = "{\"reset\": {\"seed\": 1}}\n{\"step\": {\"dir\": 1.0, \"fire\": false}}\nnonsense\n";
+        // Never dropped, so only what `serve` flushed gets through, as it
+        // would to a client waiting on each reply.
+        let mut output = std::io::BufWriter::new(Vec::new());
+        serve(&mut env, input.as_bytes(), &mut output).unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(output.get_ref().clone())
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["observation"].as_array().unwrap().len(), env.observation_len());
+        assert_eq!(lines[1]["done"], false);
+        assert!(lines[2]["error"].is_string());
+    }
+}
@@ -0,0 +1,153 @@
+use brick_breaker::{
+    campaign::{Campaign, Progress},
+    input::{Controller, MouseSettings, MouseSteering, TickInput, TickLatch},
+    level::LevelError,
+    replay::Replay,
+    score::Score,
+    state::State,
+    system::{Message, MovementSystem, PADDLE_SPEED},
+    timestep::{FixedTimestep, SIM_STEP},
+};
+
+const PROGRESS_PATH: &str = "./progress.json";
+const REPLAY_PATH: &str = "./last.replay";
+
+/// Everything about the game in progress that outlives any one scene.
+pub struct Game {
+    pub state: State,
+    pub movement: MovementSystem,
+    pub steering: MouseSteering,
+    latch: TickLatch,
+    pub messages: Vec<Message>,
+    pub timestep: FixedTimestep,
+    pub campaign: Campaign,
+    pub progress: Progress,
+    pub current_level: usize,
+    /// Lives and score as the current level began, for restarting it.
+    level_start: (u32, Score),
+    recording: Option<Replay>,
+}
+
+impl Game {
+    pub fn new(state: State, campaign: Campaign, mouse: MouseSettings) -> Self {
+        let level_start = (state.lives, state.score);
+        Self {
+            state,
+            movement: MovementSystem::new(PADDLE_SPEED),
+            steering: MouseSteering::new(mouse),
+            latch: TickLatch::default(),
+            messages: Vec::new(),
+            timestep: FixedTimestep::new(SIM_STEP, 5),
+            campaign,
+            progress: Progress::load(PROGRESS_PATH),
+            current_level: 0,
+            level_start,
+            recording: None,
+        }
+    }
+
+    /// The furthest level the player can continue from.
+    pub fn continue_level(&self) -> usize {
+        self.progress.highest_unlocked.min(self.campaign.len() - 1)
+    }
+
+    /// Starts a new game on `level` with a fresh seed and starts recording it.
+    pub fn start(&mut self, level: usize) -> Result<(), LevelError> {
+        self.current_level = level;
+        self.state.new_game();
+        let seed = rand::random();
+        log::info!("Game seed: {}", seed);
+        self.state.reseed(seed);
+        self.load_current_level()?;
+        self.recording = Some(Replay::new(seed, level, &self.state));
+        Ok(())
+    }
+
+    /// Loads `current_level`, keeping the lives and score the player has.
+    pub fn load_current_level(&mut self) -> Result<(), LevelError> {
+        let level = &self.campaign.levels[self.current_level];
+        self.state.load_level(level).map_err(|e| {
+            log::error!("Couldn't load level \"{}\": {}", level.name, e);
+            e
+        })?;
+        self.level_start = (self.state.lives, self.state.score);
+        self.timestep.reset();
+        self.latch.clear();
+        Ok(())
+    }
+
+    /// Puts lives and score back how they were and plays the level again,
+    /// recording it as a new replay.
+    pub fn restart_level(&mut self) -> Result<(), LevelError> {
+        // The replay can't follow a restart, so it ends here.
+        self.save_replay();
+        (self.state.lives, self.state.score) = self.level_start;
+        let seed = rand::random();
+        log::info!("Restart seed: {}", seed);
+        self.state.reseed(seed);
+        self.load_current_level()?;
+        self.recording = Some(Replay::new(seed, self.current_level, &self.state));
+        Ok(())
+    }
+
+    /// Moves on after the current level is cleared, unlocking the next one.
+    /// Returns `false` if that was the last level.
+    pub fn advance(&mut self) -> bool {
+        let next = self.current_level + 1;
+        if next >= self.campaign.len() {
+            return false;
+        }
+        self.current_level = next;
+        if self.progress.unlock(next) {
+            if let Err(e) = self.progress.save(PROGRESS_PATH) {
+                log::error!("Couldn't save progress: {}", e);
+            }
+        }
+        true
+    }
+
+    /// Holds on to this frame's presses for the next simulated step.
+    pub fn latch_input(&mut self, controller: &Controller) {
+        self.latch.latch(controller);
+    }
+
+    /// Runs however many fixed steps are due. `on_message` sees every message
+    /// and can stop the loop early by returning `true`.
+    pub fn simulate(&mut self, controller: &Controller, mut on_message: impl FnMut(&mut Self, Message) -> bool) {
+        for _ in 0..self.timestep.tick() {
+            let input = TickInput {
+                dir: self
+                    .steering
+                    .steer(controller, &self.state, PADDLE_SPEED, self.timestep.step()),
+                fire: self.latch.take_fire(),
+            };
+            self.movement.input(&input);
+            if let Some(replay) = &mut self.recording {
+                replay.record(self.movement.last_input());
+            }
+            self.movement.update(&mut self.state, self.timestep.step(), &mut self.messages);
+
+            let mut stop = false;
+            let mut messages = std::mem::take(&mut self.messages);
+            for msg in messages.drain(..) {
+                stop |= on_message(self, msg);
+            }
+            self.messages = messages;
+            if stop {
+                break;
+            }
+        }
+    }
+
+    /// Writes out the game being recorded, if any, so it can be played back
+    /// with the `replay` tool.
+    pub fn save_replay(&mut self) {
+        if let Some(mut replay) = self.recording.take() {
+            replay.finish(&self.state);
+            match replay.save(REPLAY_PATH) {
+                Ok(()) => log::info!("Saved replay of {} ticks to last.replay", replay.ticks),
+                Err(e) => log::error!("Couldn't save replay: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! Gamepads, read through a [`GamepadBackend`] so the rest of the game never
+//! touches hardware. [`Gamepads`] turns backend events into [`Controller`]
+//! input: bound buttons press actions, and the left stick steers.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bindings::{Binding, GamepadButton},
+    input::{Button, Controller},
+};
+
+#[cfg(target_os = "linux")]
+mod joydev;
+#[cfg(target_os = "linux")]
+pub use joydev::Joydev;
+
+/// How far the stick has to be pushed up or down to move through menus.
+const STICK_MENU_THRESHOLD: f32 = 0.5;
+
+pub type GamepadId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+    Button {
+        pad: GamepadId,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    /// A stick axis moved, to between -1 and 1. Up and right are positive.
+    Axis {
+        pad: GamepadId,
+        axis: StickAxis,
+        value: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StickAxis {
+    LeftX,
+    LeftY,
+}
+
+/// Where gamepad events come from.
+pub trait GamepadBackend {
+    /// Appends everything that happened since the last poll. Must not block.
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>);
+}
+
+/// For platforms without a backend.
+pub struct NoGamepads;
+
+impl GamepadBackend for NoGamepads {
+    fn poll(&mut self, _events: &mut Vec<GamepadEvent>) {}
+}
+
+/// Hands out events pushed to it, for driving gamepad input without any
+/// hardware.
+#[derive(Debug, Default)]
+pub struct SyntheticGamepads {
+    queue: VecDeque<GamepadEvent>,
+}
+
+impl SyntheticGamepads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: GamepadEvent) {
+        self.queue.push_back(event);
+    }
+}
+
+impl GamepadBackend for SyntheticGamepads {
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+        events.extend(self.queue.drain(..));
+    }
+}
+
+/// The best backend for this platform.
+pub fn default_backend() -> Box<dyn GamepadBackend> {
+    #[cfg(target_os = "linux")]
+    return Box::new(Joydev::new());
+    #[cfg(not(target_os = "linux"))]
+    Box::new(NoGamepads)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadSettings {
+    /// Stick travel, from 0 to 1, that's ignored around the center.
+    pub deadzone: f32,
+    /// Exponent applied to the stick past the deadzone. Above 1 gives finer
+    /// control near the center.
+    pub curve: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.15,
+            curve: 1.5,
+        }
+    }
+}
+
+impl GamepadSettings {
+    /// Maps a raw stick value through the deadzone and response curve.
+    pub fn shape(&self, value: f32) -> f32 {
+        let deadzone = self.deadzone.clamp(0.0, 0.99);
+        let magnitude = ((value.abs() - deadzone) / (1.0 - deadzone)).clamp(0.0, 1.0);
+        magnitude.powf(self.curve.max(0.1)).copysign(value)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Pad {
+    held: HashSet<GamepadButton>,
+    x: f32,
+    y: f32,
+}
+
+/// Every connected gamepad, fed into a [`Controller`].
+pub struct Gamepads {
+    backend: Box<dyn GamepadBackend>,
+    pub settings: GamepadSettings,
+    pads: HashMap<GamepadId, Pad>,
+    events: Vec<GamepadEvent>,
+}
+
+impl Gamepads {
+    pub fn new(backend: Box<dyn GamepadBackend>, settings: GamepadSettings) -> Self {
+        Self {
+            backend,
+            settings,
+            pads: HashMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Polls the backend and applies what happened to `controller`.
+    pub fn update(&mut self, controller: &mut Controller) {
+        self.backend.poll(&mut self.events);
+        for event in self.events.drain(..) {
+            match event {
+                GamepadEvent::Connected(pad) => {
+                    log::info!("Gamepad {} connected", pad);
+                    self.pads.entry(pad).or_default();
+                }
+                GamepadEvent::Disconnected(pad) => {
+                    log::info!("Gamepad {} disconnected", pad);
+                    // Let go of everything it was holding that no other pad
+                    // still holds.
+                    if let Some(old) = self.pads.remove(&pad) {
+                        for button in old.held {
+                            if !any_held(&self.pads, button) {
+                                controller.press(&Binding::Gamepad(button), false);
+                            }
+                        }
+                        if old.y.abs() >= STICK_MENU_THRESHOLD {
+                            set_menu_stick(controller, old.y, 0.0);
+                        }
+                    }
+                }
+                GamepadEvent::Button { pad, button, pressed } => {
+                    // The action stays held while any pad holds the button.
+                    let was_held = any_held(&self.pads, button);
+                    let held = &mut self.pads.entry(pad).or_default().held;
+                    if pressed {
+                        held.insert(button);
+                    } else {
+                        held.remove(&button);
+                    }
+                    let now_held = any_held(&self.pads, button);
+                    if was_held != now_held {
+                        controller.press(&Binding::Gamepad(button), now_held);
+                    }
+                }
+                GamepadEvent::Axis { pad, axis, value } => {
+                    let value = self.settings.shape(value);
+                    let state = self.pads.entry(pad).or_default();
+                    match axis {
+                        StickAxis::LeftX => state.x = value,
+                        StickAxis::LeftY => {
+                            set_menu_stick(controller, state.y, value);
+                            state.y = value;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Whichever stick is pushed furthest steers.
+        let x = self
+            .pads
+            .values()
+            .map(|p| p.x)
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+            .unwrap_or(0.0);
+        controller.set_stick(x);
+    }
+
+    pub fn connected(&self) -> usize {
+        self.pads.len()
+    }
+}
+
+/// Whether any connected pad is holding `button`.
+fn any_held(pads: &HashMap<GamepadId, Pad>, button: GamepadButton) -> bool {
+    pads.values().any(|p| p.held.contains(&button))
+}
+
+/// Presses up or down as the stick crosses the menu threshold.
+fn set_menu_stick(controller: &mut Controller, old: f32, new: f32) {
+    let zone = |y: f32| {
+        if y >= STICK_MENU_THRESHOLD {
+            Some(Button::Up)
+        } else if y <= -STICK_MENU_THRESHOLD {
+            Some(Button::Down)
+        } else {
+            None
+        }
+    };
+    let (old, new) = (zone(old), zone(new));
+    if old == new {
+        return;
+    }
+    if let Some(button) = old {
+        controller.set_button(button, false);
+    }
+    if let Some(button) = new {
+        controller.set_button(button, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gamepads(events: &[GamepadEvent]) -> Gamepads {
+        let mut backend = SyntheticGamepads::new();
+        for &event in events {
+            backend.push(event);
+        }
+        Gamepads::new(Box::new(backend), GamepadSettings::default())
+    }
+
+    fn stick(value: f32) -> GamepadEvent {
+        GamepadEvent::Axis {
+            pad: 0,
+            axis: StickAxis::LeftX,
+            value,
+        }
+    }
+
+    #[test]
+    fn stick_has_a_deadzone_and_curve() {
+        let settings = GamepadSettings {
+            deadzone: 0.2,
+            curve: 2.0,
+        };
+        assert_eq!(settings.shape(0.15), 0.0);
+        assert_eq!(settings.shape(-0.2), 0.0);
+        assert!((settings.shape(0.6) - 0.25).abs() < 1e-6);
+        assert!((settings.shape(-0.6) + 0.25).abs() < 1e-6);
+        assert_eq!(settings.shape(1.0), 1.0);
+    }
+
+    #[test]
+    fn stick_steers_and_buttons_press_their_bindings() {
+        let mut controller = Controller::new();
+        let mut pads = gamepads(&[
+            GamepadEvent::Connected(0),
+            stick(-1.0),
+            GamepadEvent::Button {
+                pad: 0,
+                button: GamepadButton::South,
+                pressed: true,
+            },
+        ]);
+        pads.update(&mut controller);
+        assert_eq!(controller.dir(), -1.0);
+        assert!(controller.fire_just_pressed());
+        assert!(!controller.back_just_pressed());
+
+        let mut pads = gamepads(&[GamepadEvent::Button {
+            pad: 0,
+            button: GamepadButton::Start,
+            pressed: true,
+        }]);
+        pads.update(&mut controller);
+        assert!(controller.back_just_pressed());
+    }
+
+    #[test]
+    fn unplugging_lets_go_of_everything() {
+        let mut controller = Controller::new();
+        let mut backend = SyntheticGamepads::new();
+        backend.push(GamepadEvent::Connected(3));
+        backend.push(GamepadEvent::Button {
+            pad: 3,
+            button: GamepadButton::DPadRight,
+            pressed: true,
+        });
+        let mut pads = Gamepads::new(Box::new(backend), GamepadSettings::default());
+        pads.update(&mut controller);
+        assert_eq!(controller.dir(), 1.0);
+        assert_eq!(pads.connected(), 1);
+
+        let mut backend = SyntheticGamepads::new();
+        backend.push(GamepadEvent::Disconnected(3));
+        pads.backend = Box::new(backend);
+        pads.update(&mut controller);
+        assert_eq!(controller.dir(), 0.0);
+        assert_eq!(pads.connected(), 0);
+    }
+
+    #[test]
+    fn button_stays_held_while_any_pad_holds_it() {
+        let mut controller = Controller::new();
+        let fire = |pad, pressed| GamepadEvent::Button {
+            pad,
+            button: GamepadButton::South,
+            pressed,
+        };
+        let mut pads = gamepads(&[
+            GamepadEvent::Connected(0),
+            GamepadEvent::Connected(1),
+            fire(0, true),
+            fire(1, true),
+            fire(0, false),
+        ]);
+        pads.update(&mut controller);
+        assert!(controller.held(Button::Fire));
+
+        let mut backend = SyntheticGamepads::new();
+        backend.push(GamepadEvent::Disconnected(1));
+        pads.backend = Box::new(backend);
+        pads.update(&mut controller);
+        assert!(!controller.held(Button::Fire));
+    }
+}
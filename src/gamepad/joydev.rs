@@ -0,0 +1,180 @@
+//! Gamepads through the Linux joystick API (`/dev/input/js*`). Each device
+//! gets a thread doing blocking reads; the game polls a channel.
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::Read,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{GamepadBackend, GamepadEvent, GamepadId, StickAxis};
+use crate::bindings::GamepadButton;
+
+/// Device numbers checked for newly plugged in pads.
+const MAX_DEVICES: usize = 16;
+/// How often to look for newly plugged in pads.
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+const EVENT_BUTTON: u8 = 0x01;
+const EVENT_AXIS: u8 = 0x02;
+/// Set on the synthetic events describing the initial state.
+const EVENT_INIT: u8 = 0x80;
+
+pub struct Joydev {
+    sender: Sender<GamepadEvent>,
+    receiver: Receiver<GamepadEvent>,
+    open: Arc<Mutex<HashSet<GamepadId>>>,
+    last_scan: Option<Instant>,
+}
+
+impl Joydev {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            open: Arc::default(),
+            last_scan: None,
+        }
+    }
+
+    fn scan(&mut self) {
+        for pad in 0..MAX_DEVICES {
+            if self.open.lock().unwrap().contains(&pad) {
+                continue;
+            }
+            let path = PathBuf::from(format!("/dev/input/js{}", pad));
+            let Ok(file) = File::open(&path) else {
+                continue;
+            };
+            self.open.lock().unwrap().insert(pad);
+            let _ = self.sender.send(GamepadEvent::Connected(pad));
+
+            let sender = self.sender.clone();
+            let open = self.open.clone();
+            std::thread::spawn(move || read_events(pad, file, sender, open));
+        }
+    }
+}
+
+impl Default for Joydev {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GamepadBackend for Joydev {
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+        let due = match self.last_scan {
+            Some(last) => last.elapsed() >= SCAN_INTERVAL,
+            None => true,
+        };
+        if due {
+            self.last_scan = Some(Instant::now());
+            self.scan();
+        }
+        events.extend(self.receiver.try_iter());
+    }
+}
+
+fn read_events(pad: GamepadId, mut file: File, sender: Sender<GamepadEvent>, open: Arc<Mutex<HashSet<GamepadId>>>) {
+    let mut buf = [0; 8];
+    while file.read_exact(&mut buf).is_ok() {
+        for event in decode(pad, buf) {
+            if sender.send(event).is_err() {
+                // The game has shut down.
+                return;
+            }
+        }
+    }
+    open.lock().unwrap().remove(&pad);
+    let _ = sender.send(GamepadEvent::Disconnected(pad));
+}
+
+/// Turns one `js_event` into ours, using the layout of Xbox-style pads.
+fn decode(pad: GamepadId, buf: [u8; 8]) -> Vec<GamepadEvent> {
+    let value = i16::from_ne_bytes([buf[4], buf[5]]);
+    let kind = buf[6] & !EVENT_INIT;
+    let number = buf[7];
+    let button = |button, pressed| GamepadEvent::Button { pad, button, pressed };
+    let axis = |axis, value: f32| GamepadEvent::Axis { pad, axis, value };
+    let scaled = (value as f32 / i16::MAX as f32).clamp(-1.0, 1.0);
+
+    match (kind, number) {
+        (EVENT_BUTTON, n) => {
+            let pressed = value != 0;
+            let mapped = match n {
+                0 => GamepadButton::South,
+                1 => GamepadButton::East,
+                2 => GamepadButton::West,
+                3 => GamepadButton::North,
+                4 => GamepadButton::LeftShoulder,
+                5 => GamepadButton::RightShoulder,
+                6 => GamepadButton::Select,
+                7 => GamepadButton::Start,
+                _ => return Vec::new(),
+            };
+            vec![button(mapped, pressed)]
+        }
+        (EVENT_AXIS, 0) => vec![axis(StickAxis::LeftX, scaled)],
+        // The joystick API has down as positive.
+        (EVENT_AXIS, 1) => vec![axis(StickAxis::LeftY, -scaled)],
+        // The d-pad comes through as a hat: two axes that are -1, 0 or 1.
+        (EVENT_AXIS, 6) => vec![
+            button(GamepadButton::DPadLeft, value < 0),
+            button(GamepadButton::DPadRight, value > 0),
+        ],
+        (EVENT_AXIS, 7) => vec![
+            button(GamepadButton::DPadUp, value < 0),
+            button(GamepadButton::DPadDown, value > 0),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(value: i16, kind: u8, number: u8) -> [u8; 8] {
+        let v = value.to_ne_bytes();
+        [0, 0, 0, 0, v[0], v[1], kind, number]
+    }
+
+    #[test]
+    fn decodes_buttons_sticks_and_hat() {
+        assert_eq!(
+            decode(1, raw(1, EVENT_BUTTON | EVENT_INIT, 7)),
+            vec![GamepadEvent::Button {
+                pad: 1,
+                button: GamepadButton::Start,
+                pressed: true
+            }]
+        );
+        assert_eq!(
+            decode(1, raw(i16::MAX, EVENT_AXIS, 1)),
+            vec![GamepadEvent::Axis {
+                pad: 1,
+                axis: StickAxis::LeftY,
+                value: -1.0
+            }]
+        );
+        let hat = decode(1, raw(-32767, EVENT_AXIS, 6));
+        assert!(hat.contains(&GamepadEvent::Button {
+            pad: 1,
+            button: GamepadButton::DPadLeft,
+            pressed: true
+        }));
+        assert!(hat.contains(&GamepadEvent::Button {
+            pad: 1,
+            button: GamepadButton::DPadRight,
+            pressed: false
+        }));
+    }
+}
@@ -0,0 +1,49 @@
+use brick_breaker::state::State;
+
+use crate::{
+    menu::Font,
+    render::{self, Sprite},
+};
+
+/// Height of the strip at the top of the screen reserved for the HUD.
+pub const HUD_HEIGHT: f32 = 6.0;
+
+pub struct Hud {
+    life: Sprite,
+    screen_size: glam::Vec2,
+    atlas_size: glam::Vec2,
+}
+
+impl Hud {
+    pub fn new(atlas: &render::TextureAtlas, screen_size: glam::Vec2) -> Self {
+        Self {
+            life: *atlas.get_sprite("ball").unwrap(),
+            screen_size,
+            atlas_size: atlas.size(),
+        }
+    }
+
+    pub fn layout(&self, device: &wgpu::Device, font: &Font, state: &State) -> render::Mesh {
+        let padding = 1.0;
+        let top = self.screen_size.y - HUD_HEIGHT + padding;
+
+        let mut data = Vec::new();
+        for i in 0..state.lives {
+            let x = padding + i as f32 * (self.life.size.x + padding);
+            data.push((glam::vec2(x, top), &self.life));
+        }
+
+        let score = state.score.total.to_string();
+        let x = self.screen_size.x - padding - font.width(&score);
+        font.place(&score, glam::vec2(x, top), false, &mut data);
+
+        let multiplier = state.score.multiplier();
+        if multiplier > 1 {
+            let combo = format!("x{}", multiplier);
+            let x = ((self.screen_size.x - font.width(&combo)) * 0.5).floor();
+            font.place(&combo, glam::vec2(x, top), true, &mut data);
+        }
+
+        render::Mesh::from_sprites_with_positions(device, self.atlas_size, &data)
+    }
+}
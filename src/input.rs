@@ -1,111 +1,585 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "game")]
 use winit::event::{DeviceEvent, ElementState, VirtualKeyCode};
 
+use crate::{
+    bindings::{Binding, Bindings},
+    state::State,
+};
+
+#[cfg(feature = "game")]
 #[derive(Debug)]
 pub enum Input {
     Device(DeviceEvent),
     KeyboardInput(VirtualKeyCode, bool),
+    /// The cursor moved over the window, to this fraction of its width.
+    CursorMoved(f32),
+}
+
+/// Anything that can steer the paddle. Implemented by [`Controller`] for
+/// live play and by [`TickInput`] for replays.
+pub trait Controls {
+    /// Paddle direction from -1 (left) to 1 (right).
+    fn dir(&self) -> f32;
+    fn fire_just_pressed(&self) -> bool;
+}
+
+/// Everything the simulation reads from the player during one tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TickInput {
+    pub dir: f32,
+    pub fire: bool,
+}
+
+impl Controls for TickInput {
+    fn dir(&self) -> f32 {
+        self.dir
+    }
+
+    fn fire_just_pressed(&self) -> bool {
+        self.fire
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// The logical buttons a [`Controller`] tracks, independent of any device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Button {
+    Left,
+    Right,
+    Up,
+    Down,
+    Fire,
+    Back,
+}
+
+impl Button {
+    pub const ALL: [Self; 6] = [Self::Left, Self::Right, Self::Up, Self::Down, Self::Fire, Self::Back];
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Controller {
+    bindings: Bindings,
+    /// The bound inputs that are down right now.
+    pressed_bindings: HashSet<Binding>,
+    last_key: Option<Binding>,
     left: Axis,
     right: Axis,
     up: Axis,
     down: Axis,
     fire: Axis,
     back: Axis,
+    /// Analog steering, e.g. from a gamepad stick.
+    stick: Axis,
+    cursor: Option<f32>,
+    mouse_motion: f64,
 }
 
 impl Controller {
     pub fn new() -> Self {
-        Self {
-            left: Default::default(),
-            right: Default::default(),
-            up: Default::default(),
-            down: Default::default(),
-            fire: Default::default(),
-            back: Default::default(),
+        Self::default()
+    }
+
+    /// Forgets the presses and releases seen so far. Call once everything
+    /// that reacts to them has had a look, i.e. once a frame.
+    pub fn clear_edges(&mut self) {
+        for button in Button::ALL {
+            self.axis_mut(button).clear_edges();
         }
+        self.last_key = None;
     }
 
-    #[allow(dead_code)]
-    pub fn reset(&mut self) {
-        self.left.new_input = false;
-        self.right.new_input = false;
-        self.fire.new_input = false;
-        self.back.new_input = false;
+    /// Whether anything was pressed or released since the edges were last
+    /// cleared.
+    pub fn has_edges(&self) -> bool {
+        Button::ALL
+            .into_iter()
+            .any(|b| self.just_pressed(b) || self.just_released(b))
+            || self.last_key.is_some()
     }
 
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.bindings = bindings;
+    }
+
+    /// Presses or releases whichever button `binding` is bound to. The button
+    /// stays held while any of its bindings are down, and presses of a
+    /// binding that's already down, like key repeats, are ignored.
+    pub fn press(&mut self, binding: &Binding, pressed: bool) {
+        let changed = if pressed {
+            self.pressed_bindings.insert(binding.clone())
+        } else {
+            self.pressed_bindings.remove(binding)
+        };
+        if !changed {
+            return;
+        }
+        if let Some(button) = self.bindings.button(binding) {
+            let held = self.pressed_bindings.iter().any(|b| self.bindings.button(b) == Some(button));
+            self.set_button(button, held);
+        }
+    }
+
+    /// Presses or releases `button` directly. Lets tools and tests drive the
+    /// game without a window.
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        self.axis_mut(button).set_digital(pressed);
+    }
+
+    fn axis(&self, button: Button) -> &Axis {
+        match button {
+            Button::Left => &self.left,
+            Button::Right => &self.right,
+            Button::Up => &self.up,
+            Button::Down => &self.down,
+            Button::Fire => &self.fire,
+            Button::Back => &self.back,
+        }
+    }
+
+    fn axis_mut(&mut self, button: Button) -> &mut Axis {
+        match button {
+            Button::Left => &mut self.left,
+            Button::Right => &mut self.right,
+            Button::Up => &mut self.up,
+            Button::Down => &mut self.down,
+            Button::Fire => &mut self.fire,
+            Button::Back => &mut self.back,
+        }
+    }
+
+    /// Sets analog steering, from -1 (full left) to 1 (full right). Adds to
+    /// the digital left and right buttons.
+    pub fn set_stick(&mut self, value: f32) {
+        self.stick.set(value.clamp(-1.0, 1.0));
+    }
+
+    /// Puts the cursor at `x`, a fraction of the window's width.
+    pub fn set_cursor(&mut self, x: f32) {
+        self.cursor = Some(x.clamp(0.0, 1.0));
+    }
+
+    /// Moves the mouse `dx` pixels sideways, wherever the cursor is.
+    pub fn move_mouse(&mut self, dx: f64) {
+        self.mouse_motion += dx;
+    }
+
+    #[cfg(feature = "game")]
     pub fn input(&mut self, event: &Input) {
         match event {
-            Input::KeyboardInput(key, pressed) => match key {
-                VirtualKeyCode::A | VirtualKeyCode::Left => self.left.set_digital(*pressed),
-                VirtualKeyCode::D | VirtualKeyCode::Right => self.right.set_digital(*pressed),
-                VirtualKeyCode::W | VirtualKeyCode::Up => self.up.set_digital(*pressed),
-                VirtualKeyCode::S | VirtualKeyCode::Down => self.down.set_digital(*pressed),
-                VirtualKeyCode::Space | VirtualKeyCode::Return => self.fire.set_digital(*pressed),
-                VirtualKeyCode::Escape => self.back.set_digital(*pressed),
-                _ => (),
+            Input::KeyboardInput(key, pressed) => {
+                let binding = Binding::key(*key);
+                let repeat = *pressed && self.pressed_bindings.contains(&binding);
+                self.press(&binding, *pressed);
+                if *pressed && !repeat && self.last_key.is_none() {
+                    self.last_key = Some(binding);
+                }
             }
-            Input::Device(DeviceEvent::Button { button, state }) => match (button, *state == ElementState::Pressed) {
-                (0, pressed) => self.fire.set_digital(pressed),
-                _ => (),
-            },
+            Input::Device(DeviceEvent::Button { button, state }) => {
+                self.press(&Binding::Mouse(*button), *state == ElementState::Pressed)
+            }
+            Input::Device(DeviceEvent::MouseMotion { delta: (dx, _) }) => self.move_mouse(*dx),
+            Input::CursorMoved(x) => self.set_cursor(*x),
             _ => (),
         }
     }
 
     pub fn dir(&self) -> f32 {
-        self.right.value - self.left.value
+        (self.right.value - self.left.value + self.stick.value).clamp(-1.0, 1.0)
     }
 
-    #[allow(dead_code)]
     pub fn fire(&self) -> f32 {
         self.fire.value
     }
 
+    /// Whether `button` went down since the edges were last cleared, even if
+    /// it has been let go again since.
+    pub fn just_pressed(&self, button: Button) -> bool {
+        self.axis(button).just_pressed
+    }
+
+    pub fn held(&self, button: Button) -> bool {
+        self.axis(button).held
+    }
+
+    /// Whether `button` came up since the edges were last cleared.
+    pub fn just_released(&self, button: Button) -> bool {
+        self.axis(button).just_released
+    }
+
     pub fn fire_just_pressed(&self) -> bool {
-        self.fire.new_input
+        self.just_pressed(Button::Fire)
     }
 
     pub fn back_just_pressed(&self) -> bool {
-        self.back.new_input
+        self.just_pressed(Button::Back)
     }
 
     pub fn up_just_pressed(&self) -> bool {
-        self.up.new_input
+        self.just_pressed(Button::Up)
     }
 
     pub fn down_just_pressed(&self) -> bool {
-        self.down.new_input
+        self.just_pressed(Button::Down)
+    }
+
+    /// The first key pressed since the edges were last cleared. Lets menus
+    /// capture a key to bind.
+    pub fn last_key(&self) -> Option<&Binding> {
+        self.last_key.as_ref()
+    }
+
+    /// Where the cursor last was over the window, as a fraction of its width.
+    pub fn cursor(&self) -> Option<f32> {
+        self.cursor
+    }
+
+    /// Total sideways mouse motion so far, in pixels.
+    pub fn mouse_motion(&self) -> f64 {
+        self.mouse_motion
+    }
+}
+
+impl Controls for Controller {
+    fn dir(&self) -> f32 {
+        Controller::dir(self)
+    }
+
+    fn fire_just_pressed(&self) -> bool {
+        Controller::fire_just_pressed(self)
+    }
+}
+
+/// How the mouse steers the paddle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MouseMode {
+    Off,
+    /// The paddle follows the cursor.
+    #[default]
+    Absolute,
+    /// Moving the mouse moves the paddle, wherever the cursor is. Meant to be
+    /// used with the cursor grabbed.
+    Relative,
+}
+
+impl MouseMode {
+    /// The mode after this one, for cycling through them in a menu.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Absolute,
+            Self::Absolute => Self::Relative,
+            Self::Relative => Self::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseSettings {
+    pub mode: MouseMode,
+    /// Scales mouse motion in relative mode.
+    pub sensitivity: f32,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            mode: MouseMode::default(),
+            sensitivity: 1.0,
+        }
     }
 }
 
+/// Arena widths the paddle moves per pixel of mouse motion, at sensitivity 1.
+const RELATIVE_SCALE: f32 = 1.0 / 800.0;
+/// The fastest the mouse can move the paddle, as a multiple of the speed the
+/// keyboard moves it at.
+pub const MAX_MOUSE_DIR: f32 = 3.0;
+
+/// Turns mouse movement into paddle directions, so the simulation and
+/// replays still only ever see a [`TickInput`]. The keys take over whenever
+/// they're held, until the mouse moves again.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MouseSteering {
+    pub settings: MouseSettings,
+    /// Where the mouse wants the center of the paddle, in arena units.
+    target: Option<f32>,
+    cursor_seen: Option<f32>,
+    motion_seen: f64,
+}
+
+impl MouseSteering {
+    pub fn new(settings: MouseSettings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    /// Forgets the mouse target and any motion or cursor movement so far.
+    /// Call when play starts or resumes, so the mouse moving around menus
+    /// doesn't carry over to the paddle.
+    pub fn resync(&mut self, controller: &Controller) {
+        self.target = None;
+        self.cursor_seen = controller.cursor();
+        self.motion_seen = controller.mouse_motion();
+    }
+
+    /// The paddle direction for the next tick: `controller`'s, but heading
+    /// for the mouse if it's steering. `speed` and `dt` are the paddle speed
+    /// and tick length.
+    pub fn steer(&mut self, controller: &Controller, state: &State, speed: f32, dt: f32) -> f32 {
+        let paddle = &state.player.body;
+        let half_width = paddle.size.x * 0.5;
+        let center = paddle.pos.x + half_width;
+        let arena_width = state.arena_size.x;
+
+        let cursor = controller.cursor();
+        let motion = (controller.mouse_motion() - self.motion_seen) as f32;
+        self.motion_seen = controller.mouse_motion();
+        match self.settings.mode {
+            MouseMode::Off => (),
+            MouseMode::Absolute => {
+                if cursor != self.cursor_seen {
+                    self.target = cursor.map(|x| x * arena_width);
+                }
+            }
+            MouseMode::Relative => {
+                if motion != 0.0 {
+                    let offset = motion * self.settings.sensitivity * RELATIVE_SCALE * arena_width;
+                    self.target = Some(self.target.unwrap_or(center) + offset);
+                }
+            }
+        }
+        self.cursor_seen = cursor;
+
+        let keys = controller.dir();
+        if keys != 0.0 {
+            self.target = None;
+        }
+        match self.target {
+            Some(target) => {
+                // Clamped so moving the mouse past a wall doesn't have to be
+                // undone before the paddle comes back.
+                let target = target.clamp(half_width, (arena_width - half_width).max(half_width));
+                self.target = Some(target);
+                ((target - center) / (speed * dt)).clamp(-MAX_MOUSE_DIR, MAX_MOUSE_DIR)
+            }
+            None => keys,
+        }
+    }
+}
+
+/// Holds on to presses from a frame until a simulation tick reads them. A
+/// frame can run no ticks or several, and each press should reach exactly
+/// one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TickLatch {
+    fire: bool,
+}
+
+impl TickLatch {
+    /// Picks up `controller`'s presses. Call before its edges are cleared.
+    pub fn latch(&mut self, controller: &Controller) {
+        self.fire |= controller.fire_just_pressed();
+    }
+
+    /// Whether fire was pressed since the last tick that took it.
+    pub fn take_fire(&mut self) -> bool {
+        std::mem::take(&mut self.fire)
+    }
+
+    /// Drops anything latched, e.g. presses left over from a menu.
+    pub fn clear(&mut self) {
+        self.fire = false;
+    }
+}
+
+/// One input's current value, plus the presses and releases since its edges
+/// were last cleared. Edges latch, so a press and release between two reads
+/// still shows up as both.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Axis {
     value: f32,
-    new_input: bool,
+    held: bool,
+    just_pressed: bool,
+    just_released: bool,
 }
 
 impl Axis {
-    #[allow(dead_code)]
+    /// Sets an analog value. Counts as held while above zero.
     pub fn set(&mut self, value: f32) {
+        let held = value > 0.0;
+        if held && !self.held {
+            self.just_pressed = true;
+        } else if !held && self.held {
+            self.just_released = true;
+        }
         self.value = value;
-        self.new_input = value > 0.0;
+        self.held = held;
     }
 
     pub fn set_digital(&mut self, pressed: bool) {
-        self.value = if pressed { 1.0 } else { 0.0 };
-        self.new_input = pressed;
+        self.set(if pressed { 1.0 } else { 0.0 });
+    }
+
+    pub fn clear_edges(&mut self) {
+        self.just_pressed = false;
+        self.just_released = false;
     }
 
-    #[allow(dead_code)]
     pub fn press(&mut self) {
         self.set(1.0);
     }
     
-    #[allow(dead_code)]
     pub fn release(&mut self) {
         self.set(0.0);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{system::PADDLE_SPEED, timestep::SIM_STEP};
+
+    fn state() -> State {
+        let mut state = State::new(
+            glam::vec2(80.0, 74.0),
+            glam::vec2(16.0, 3.0),
+            glam::vec2(4.0, 4.0),
+            glam::vec2(8.0, 4.0),
+        );
+        state.player.body.pos.x = 32.0;
+        state
+    }
+
+    #[test]
+    fn absolute_mouse_heads_for_the_cursor_until_keys_are_used() {
+        let state = state();
+        let mut steering = MouseSteering::new(MouseSettings::default());
+        let mut controller = Controller::new();
+
+        // Paddle center is at 40, the cursor a little to the right of it.
+        controller.set_cursor(0.505);
+        let dir = steering.steer(&controller, &state, PADDLE_SPEED, SIM_STEP);
+        let expected = (0.505 * 80.0 - 40.0) / (PADDLE_SPEED * SIM_STEP);
+        assert!((dir - expected).abs() < 1e-3, "{}", dir);
+
+        controller.set_cursor(1.0);
+        let dir = steering.steer(&controller, &state, PADDLE_SPEED, SIM_STEP);
+        assert_eq!(dir, MAX_MOUSE_DIR);
+
+        controller.set_button(Button::Left, true);
+        assert_eq!(steering.steer(&controller, &state, PADDLE_SPEED, SIM_STEP), -1.0);
+        // The cursor hasn't moved since, so the keys keep control.
+        controller.set_button(Button::Left, false);
+        assert_eq!(steering.steer(&controller, &state, PADDLE_SPEED, SIM_STEP), 0.0);
+    }
+
+    #[test]
+    fn relative_mouse_moves_the_paddle_by_the_motion() {
+        let state = state();
+        let mut steering = MouseSteering::new(MouseSettings {
+            mode: MouseMode::Relative,
+            sensitivity: 2.0,
+        });
+        let mut controller = Controller::new();
+        // Where the cursor is makes no difference.
+        controller.set_cursor(0.0);
+        controller.move_mouse(-2.0);
+
+        let dir = steering.steer(&controller, &state, PADDLE_SPEED, SIM_STEP);
+        let moved = -2.0 * 2.0 * RELATIVE_SCALE * 80.0;
+        assert!((dir - moved / (PADDLE_SPEED * SIM_STEP)).abs() < 1e-3, "{}", dir);
+    }
+
+    #[test]
+    fn mouse_moved_while_paused_is_ignored() {
+        let state = state();
+        for mode in [MouseMode::Relative, MouseMode::Absolute] {
+            let mut steering = MouseSteering::new(MouseSettings { mode, sensitivity: 1.0 });
+            let mut controller = Controller::new();
+            controller.set_cursor(0.5);
+            assert_eq!(steering.steer(&controller, &state, PADDLE_SPEED, SIM_STEP), 0.0);
+
+            // Paused, wandering around the menus.
+            controller.move_mouse(500.0);
+            controller.set_cursor(0.9);
+            steering.resync(&controller);
+
+            assert_eq!(steering.steer(&controller, &state, PADDLE_SPEED, SIM_STEP), 0.0, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn tap_within_a_frame_is_latched() {
+        let mut controller = Controller::new();
+        controller.set_button(Button::Fire, true);
+        controller.set_button(Button::Fire, false);
+
+        assert!(controller.just_pressed(Button::Fire));
+        assert!(controller.just_released(Button::Fire));
+        assert!(!controller.held(Button::Fire));
+        assert!(controller.has_edges());
+
+        controller.clear_edges();
+        assert!(!controller.just_pressed(Button::Fire));
+        assert!(!controller.just_released(Button::Fire));
+        assert!(!controller.has_edges());
+    }
+
+    #[test]
+    fn holding_a_button_presses_it_once() {
+        let mut controller = Controller::new();
+        controller.set_button(Button::Fire, true);
+        controller.clear_edges();
+        // Key repeat sends more presses while held.
+        controller.set_button(Button::Fire, true);
+
+        assert!(controller.held(Button::Fire));
+        assert!(!controller.just_pressed(Button::Fire));
+        assert!(!controller.has_edges());
+    }
+
+    #[test]
+    fn an_action_stays_held_while_any_of_its_bindings_is() {
+        let mut controller = Controller::new();
+        let a = Binding::Key("A".to_string());
+        let left = Binding::Key("Left".to_string());
+
+        // Hold A and tap Left.
+        controller.press(&a, true);
+        controller.press(&left, true);
+        controller.press(&left, false);
+        assert!(controller.held(Button::Left));
+        assert_eq!(controller.dir(), -1.0);
+
+        // A repeat doesn't count as holding A twice.
+        controller.press(&a, true);
+        controller.press(&a, false);
+        assert!(!controller.held(Button::Left));
+    }
+
+    #[test]
+    fn each_press_reaches_exactly_one_tick() {
+        let mut controller = Controller::new();
+        let mut latch = TickLatch::default();
+
+        // Tapped during a frame that runs no ticks.
+        controller.set_button(Button::Fire, true);
+        controller.set_button(Button::Fire, false);
+        latch.latch(&controller);
+        controller.clear_edges();
+
+        // The next frame runs two.
+        latch.latch(&controller);
+        controller.clear_edges();
+        assert!(latch.take_fire());
+        assert!(!latch.take_fire());
+    }
+}
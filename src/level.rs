@@ -0,0 +1,289 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::state::BrickKind;
+
+/// Highest hit count a brick can have. Each count has its own sprite.
+pub const MAX_BRICK_HITS: u32 = 4;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LevelError {
+    #[error("couldn't read level file {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("in {path}: {source}")]
+    InFile {
+        path: String,
+        source: Box<LevelError>,
+    },
+    #[error("malformed level: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("level has no bricks")]
+    Empty,
+    #[error("level has no bricks that can be broken")]
+    Unwinnable,
+    #[error("campaign has no levels")]
+    EmptyCampaign,
+    #[error("row {row}, column {col}: '{ch}' isn't in the legend")]
+    UnknownBrick { row: usize, col: usize, ch: char },
+    #[error("legend entry '{ch}' has {hits} hits, expected 1 to {max}")]
+    InvalidHits { ch: char, hits: u32, max: u32 },
+    #[error("grid is {cols} bricks wide but only {max} fit in the arena")]
+    TooWide { cols: usize, max: usize },
+    #[error("grid is {rows} bricks tall but only {max} fit in the arena")]
+    TooTall { rows: usize, max: usize },
+    #[error("drop rate must be between 0 and 1, got {0}")]
+    InvalidDropRate(f32),
+    #[error("ball speed must be a positive number, got {0}")]
+    InvalidBallSpeed(f32),
+    #[error("paddle width must be more than {min} and at most {max}, got {width}")]
+    InvalidPaddleWidth { width: f32, min: f32, max: f32 },
+}
+
+/// A level as stored in `assets/levels/*.ron`.
+///
+/// Bricks are laid out as a character grid, top row first. Each character is
+/// looked up in the `legend` to find out what kind of brick goes there, while
+/// `.` and spaces leave the cell empty.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Level {
+    pub name: String,
+    #[serde(default = "default_ball_speed")]
+    pub ball_speed: f32,
+    #[serde(default = "default_paddle_width")]
+    pub paddle_width: f32,
+    /// Chance of a destroyed brick dropping a power-up, from 0 to 1.
+    #[serde(default = "default_drop_rate")]
+    pub drop_rate: f32,
+    /// Music track to play instead of the usual one, by its name in
+    /// `assets/music.json`.
+    #[serde(default)]
+    pub music: Option<String>,
+    pub legend: HashMap<char, BrickDef>,
+    pub grid: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+pub struct BrickDef {
+    pub hits: u32,
+    #[serde(default)]
+    pub kind: BrickKind,
+}
+
+/// A brick cell from the grid, in rows and columns from the top left.
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedBrick {
+    pub row: usize,
+    pub col: usize,
+    pub def: BrickDef,
+}
+
+fn default_ball_speed() -> f32 {
+    5.0
+}
+
+fn default_paddle_width() -> f32 {
+    16.0
+}
+
+fn default_drop_rate() -> f32 {
+    0.1
+}
+
+impl Level {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).map_err(|source| LevelError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::from_ron(&data).map_err(|e| LevelError::InFile {
+            path: path.display().to_string(),
+            source: Box::new(e),
+        })
+    }
+
+    pub fn from_ron(data: &str) -> Result<Self, LevelError> {
+        Ok(ron::from_str(data)?)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.grid.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.grid.iter().map(|r| r.chars().count()).max().unwrap_or(0)
+    }
+
+    /// Resolves every non-empty grid cell against the legend.
+    pub fn bricks(&self) -> Result<Vec<PlacedBrick>, LevelError> {
+        let mut bricks = Vec::new();
+        for (row, line) in self.grid.iter().enumerate() {
+            for (col, ch) in line.chars().enumerate() {
+                if ch == '.' || ch == ' ' {
+                    continue;
+                }
+                let def = *self
+                    .legend
+                    .get(&ch)
+                    .ok_or(LevelError::UnknownBrick { row, col, ch })?;
+                if def.hits == 0 || def.hits > MAX_BRICK_HITS {
+                    return Err(LevelError::InvalidHits {
+                        ch,
+                        hits: def.hits,
+                        max: MAX_BRICK_HITS,
+                    });
+                }
+                bricks.push(PlacedBrick { row, col, def });
+            }
+        }
+        if bricks.is_empty() {
+            return Err(LevelError::Empty);
+        }
+        if !bricks.iter().any(|b| b.def.kind.counts_toward_win()) {
+            return Err(LevelError::Unwinnable);
+        }
+        Ok(bricks)
+    }
+
+    /// Checks that the level fits in an arena of the given size.
+    pub fn validate(
+        &self,
+        arena_size: glam::Vec2,
+        brick_size: glam::Vec2,
+        ball_size: glam::Vec2,
+    ) -> Result<(), LevelError> {
+        self.bricks()?;
+
+        let max_cols = (arena_size.x / brick_size.x) as usize;
+        if self.cols() > max_cols {
+            return Err(LevelError::TooWide {
+                cols: self.cols(),
+                max: max_cols,
+            });
+        }
+        // Leave room for the paddle and the ball underneath.
+        let max_rows = ((arena_size.y * 0.5) / brick_size.y) as usize;
+        if self.rows() > max_rows {
+            return Err(LevelError::TooTall {
+                rows: self.rows(),
+                max: max_rows,
+            });
+        }
+
+        if !self.ball_speed.is_finite() || self.ball_speed <= 0.0 {
+            return Err(LevelError::InvalidBallSpeed(self.ball_speed));
+        }
+        if !(0.0..=1.0).contains(&self.drop_rate) {
+            return Err(LevelError::InvalidDropRate(self.drop_rate));
+        }
+        // The paddle bounce divides by how much wider the paddle is than the
+        // ball.
+        let min_width = ball_size.x;
+        if !(self.paddle_width > min_width && self.paddle_width <= arena_size.x) {
+            return Err(LevelError::InvalidPaddleWidth {
+                width: self.paddle_width,
+                min: min_width,
+                max: arena_size.x,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARENA_SIZE: glam::Vec2 = glam::vec2(80.0, 74.0);
+    const BRICK_SIZE: glam::Vec2 = glam::vec2(8.0, 4.0);
+    const BALL_SIZE: glam::Vec2 = glam::vec2(4.0, 4.0);
+
+    fn level(fields: &str, legend: &str, grid: &str) -> Level {
+        Level::from_ron(&format!(
+            "Level(name: \"Test\", {} legend: {{ {} }}, grid: [{}])",
+            fields, legend, grid
+        ))
+        .unwrap()
+    }
+
+    fn validate(fields: &str) -> Result<(), LevelError> {
+        level(fields, "'1': (hits: 1)", "\"1\"").validate(ARENA_SIZE, BRICK_SIZE, BALL_SIZE)
+    }
+
+    #[test]
+    fn bundled_levels_are_valid() {
+        for file in ["01.ron", "02.ron", "03.ron"] {
+            let level = Level::load(format!("./assets/levels/{}", file)).unwrap();
+            level.validate(ARENA_SIZE, BRICK_SIZE, BALL_SIZE).unwrap();
+        }
+    }
+
+    #[test]
+    fn unreadable_or_malformed_files_are_rejected() {
+        assert!(matches!(Level::load("./assets/levels/missing.ron"), Err(LevelError::Io { .. })));
+        assert!(matches!(Level::from_ron("Level(name: 1)"), Err(LevelError::Parse(_))));
+
+        let path = std::env::temp_dir().join(format!("brick_breaker_level_{}.ron", std::process::id()));
+        std::fs::write(&path, "Level(name: 1)").unwrap();
+        let result = Level::load(&path);
+        let _ = std::fs::remove_file(&path);
+        match result {
+            Err(LevelError::InFile { path: error_path, source }) => {
+                assert_eq!(error_path, path.display().to_string());
+                assert!(matches!(*source, LevelError::Parse(_)));
+            }
+            other => panic!("expected a parse error naming the file, got {:?}", other.map(|l| l.name)),
+        }
+    }
+
+    #[test]
+    fn grid_must_hold_breakable_known_bricks() {
+        let bricks = |legend, grid| level("", legend, grid).bricks();
+        assert!(matches!(bricks("'1': (hits: 1)", "\"..\""), Err(LevelError::Empty)));
+        assert!(matches!(
+            bricks("'#': (hits: 1, kind: Indestructible)", "\"##\""),
+            Err(LevelError::Unwinnable)
+        ));
+        assert!(matches!(
+            bricks("'1': (hits: 1)", "\"1x\""),
+            Err(LevelError::UnknownBrick { row: 0, col: 1, ch: 'x' })
+        ));
+        assert!(matches!(bricks("'0': (hits: 0)", "\"0\""), Err(LevelError::InvalidHits { hits: 0, .. })));
+        assert!(matches!(
+            bricks("'5': (hits: 5)", "\"5\""),
+            Err(LevelError::InvalidHits { hits: 5, max: MAX_BRICK_HITS, .. })
+        ));
+    }
+
+    #[test]
+    fn grid_must_fit_the_arena() {
+        let wide = format!("\"{}\"", "1".repeat(11));
+        assert!(matches!(
+            level("", "'1': (hits: 1)", &wide).validate(ARENA_SIZE, BRICK_SIZE, BALL_SIZE),
+            Err(LevelError::TooWide { cols: 11, max: 10 })
+        ));
+        let tall = ["\"1\""; 10].join(",");
+        assert!(matches!(
+            level("", "'1': (hits: 1)", &tall).validate(ARENA_SIZE, BRICK_SIZE, BALL_SIZE),
+            Err(LevelError::TooTall { rows: 10, max: 9 })
+        ));
+    }
+
+    #[test]
+    fn tuning_must_be_in_range() {
+        assert!(validate("drop_rate: 1.0, ball_speed: 0.5, paddle_width: 80.0,").is_ok());
+        assert!(matches!(validate("drop_rate: 1.5,"), Err(LevelError::InvalidDropRate(_))));
+        for speed in ["0.0", "-1.0", "inf", "NaN"] {
+            let result = validate(&format!("ball_speed: {},", speed));
+            assert!(matches!(result, Err(LevelError::InvalidBallSpeed(_))), "{}", speed);
+        }
+        for width in ["4.0", "2.0", "81.0"] {
+            let result = validate(&format!("paddle_width: {},", width));
+            assert!(matches!(result, Err(LevelError::InvalidPaddleWidth { .. })), "{}", width);
+        }
+        assert!(validate("paddle_width: 4.5,").is_ok());
+    }
+}
@@ -0,0 +1,21 @@
+//! The brick breaker simulation, usable without a window, GPU or audio
+//! device. Drive a [`state::State`] with a [`system::MovementSystem`] and
+//! read back the [`system::Message`]s it produces.
+//!
+//! The `game` feature (on by default) adds the winit bindings for
+//! [`input::Controller`] that the game binary needs. Build with
+//! `--no-default-features` to leave out the windowing and audio stack.
+
+pub mod autopilot;
+pub mod bindings;
+pub mod campaign;
+pub mod env;
+pub mod gamepad;
+pub mod input;
+pub mod level;
+pub mod powerup;
+pub mod replay;
+pub mod score;
+pub mod state;
+pub mod system;
+pub mod timestep;
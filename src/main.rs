@@ -1,13 +1,21 @@
+mod demo;
+mod game;
+mod hud;
 mod render;
-mod state;
+mod scene;
 mod util;
-mod system;
-mod input;
 mod menu;
 mod sound;
 
-use std::{io::Write, fs::read_to_string};
+use std::{fs::read_to_string, io::Write, time::Instant};
 
+use brick_breaker::{
+    bindings::Bindings,
+    campaign,
+    gamepad::{self, GamepadSettings, Gamepads},
+    input::{self, MouseMode, MouseSettings},
+    state,
+};
 use util::*;
 use winit::{
     dpi::LogicalSize,
@@ -16,13 +24,27 @@ use winit::{
     window::WindowBuilder,
 };
 
-use crate::{render::{BoxRenderer, TextureAtlas}, menu::Menu};
+use crate::{
+    game::Game,
+    hud::Hud,
+    menu::{Font, Menu},
+    render::{BoxRenderer, TextureAtlas},
+    scene::{Context, Gfx, MainMenu, SceneStack},
+};
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct Settings {
     fullscreen: bool,
     width: u32,
     height: u32,
+    #[serde(default)]
+    mouse: MouseSettings,
+    #[serde(default, deserialize_with = "Bindings::deserialize_or_default")]
+    bindings: Bindings,
+    #[serde(default)]
+    gamepad: GamepadSettings,
+    #[serde(default)]
+    audio: sound::AudioSettings,
 }
 
 impl Default for Settings {
@@ -31,16 +53,14 @@ impl Default for Settings {
             fullscreen: false,
             width: 800,
             height: 600,
+            mouse: MouseSettings::default(),
+            bindings: Bindings::default(),
+            gamepad: GamepadSettings::default(),
+            audio: sound::AudioSettings::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum State {
-    MainMenu,
-    Quitting,
-}
-
 async fn run() -> Result<(), anyhow::Error> {
     let mut settings = match std::fs::read_to_string("settings.json") {
         Ok(s) => serde_json::from_str(&s).unwrap_or_default(),
@@ -63,8 +83,7 @@ async fn run() -> Result<(), anyhow::Error> {
     let surface = unsafe { instance.create_surface(&window) };
     let adapter = instance
         .enumerate_adapters(wgpu::Backends::all())
-        .filter(|a| a.is_surface_supported(&surface))
-        .next()
+        .find(|a| a.is_surface_supported(&surface))
         .expect("Platform should have a supported adapter");
     let (device, queue) = adapter
         .request_device(
@@ -97,21 +116,54 @@ async fn run() -> Result<(), anyhow::Error> {
     let box_renderer =
         BoxRenderer::new(&device, surf_cfg.format, screen_size, &texture_atlas)?;
     let mut controller = input::Controller::new();
-    let mut game_state = state::State::new(
-        screen_size,
-        texture_atlas.get_sprite("player").unwrap().size,
-        texture_atlas.get_sprite("ball").unwrap().size,
-        texture_atlas.get_sprite("brick1").unwrap().size,
+    controller.set_bindings(settings.bindings.clone());
+    let mut gamepads = Gamepads::new(gamepad::default_backend(), settings.gamepad);
+    let arena_size = glam::vec2(screen_size.x, screen_size.y - hud::HUD_HEIGHT);
+    let player_size = texture_atlas.get_sprite("player").unwrap().size;
+    let ball_size = texture_atlas.get_sprite("ball").unwrap().size;
+    let brick_size = texture_atlas.get_sprite("brick1").unwrap().size;
+    let campaign = campaign::Campaign::load("./assets/levels/campaign.ron")?;
+    let mut game = Game::new(
+        state::State::new(arena_size, player_size, ball_size, brick_size),
+        campaign,
+        settings.mouse,
     );
-    let mut movement = system::MovementSystem::new(10.0);
-    let mut game_messages = Vec::new();
+    let demo = demo::Demo::new(state::State::new(arena_size, player_size, ball_size, brick_size));
 
-    let mut menu_up = true;
-    let mut menu_messages = Vec::new();
     let mut menu = Menu::new(&texture_atlas, screen_size);
+    menu.set_can_continue(game.progress.highest_unlocked > 0);
+    let mut scenes = SceneStack::new(Box::new(MainMenu::new(menu, demo)));
+    let font = Font::new(&texture_atlas);
+    let hud = Hud::new(&texture_atlas, screen_size);
+    // How the cursor is currently captured, if at all.
+    let mut cursor_mode = MouseMode::Off;
 
     let sound_config = read_to_string("./assets/sounds.json")?;
     let mut sound_system = sound::SoundSystem::with_json(&sound_config)?;
+    sound_system.settings = settings.audio;
+    match read_to_string("./assets/music.json") {
+        Ok(json) => {
+            if let Err(e) = sound_system.load_music(&json) {
+                log::error!("Couldn't load music: {}", e);
+            }
+        }
+        Err(e) => log::warn!("No music manifest: {}", e),
+    }
+    let mut last_frame = Instant::now();
+
+    // What the scenes get to work with, borrowed fresh for each event.
+    macro_rules! ctx {
+        () => {
+            &mut Context {
+                game: &mut game,
+                controller: &mut controller,
+                sound: &mut sound_system,
+                settings: &mut settings,
+                window: &window,
+                screen_size,
+            }
+        };
+    }
 
     window.set_visible(true);
     ev_loop.run(move |ev, _, control_flow| match ev {
@@ -125,6 +177,13 @@ async fn run() -> Result<(), anyhow::Error> {
                 settings.height = size.height;
                 surface.configure(&device, &surf_cfg);
             }
+            WindowEvent::CursorMoved { position, .. } => {
+                let x = position.x as f32 / surf_cfg.width.max(1) as f32;
+                controller.input(&input::Input::CursorMoved(x));
+            }
+            WindowEvent::Focused(false) if !scenes.focus_lost(ctx!()) => {
+                *control_flow = ControlFlow::Exit;
+            }
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
@@ -134,35 +193,8 @@ async fn run() -> Result<(), anyhow::Error> {
                     },
                 ..
             } => match (key, state == ElementState::Pressed) {
-                (VirtualKeyCode::F11, true) => {
-                    settings.fullscreen = !settings.fullscreen;
-                    set_fullscreen(settings.fullscreen, &window);
-                }
-                (key, pressed) => {
-                    controller.input(&input::Input::KeyboardInput(key, pressed));
-                    if menu_up {
-                        menu.input(&controller, &mut menu_messages);
-                        for msg in menu_messages.drain(..) {
-                            match msg {
-                                menu::Message::Exit => *control_flow = ControlFlow::Exit,
-                                menu::Message::Start => {
-                                    menu_up = false;
-                                    game_state.setup(10, 4);
-                                } 
-                                menu::Message::ToggleFullscreen => {
-                                    settings.fullscreen = !settings.fullscreen;
-                                    set_fullscreen(settings.fullscreen, &window);
-                                }
-                                menu::Message::FocusChanged => {
-                                    sound_system.play_sound("bounce");
-                                }
-                            }
-                        }
-                    }
-                    else if controller.back_just_pressed() {
-                        menu_up = true;
-                    }
-                },
+                (VirtualKeyCode::F11, true) => ctx!().toggle_fullscreen(),
+                (key, pressed) => controller.input(&input::Input::KeyboardInput(key, pressed)),
             },
             _ => (),
         },
@@ -175,29 +207,40 @@ async fn run() -> Result<(), anyhow::Error> {
         Event::MainEventsCleared => {}
         Event::RedrawRequested(_) => {
             window.request_redraw();
-            if !menu_up {
-                movement.input(&controller);
-                movement.update(&mut game_state, 1.0 / 60.0, &mut game_messages);
-                for msg in game_messages.drain(..) {
-                    match msg {
-                        system::Message::Win => {
-                            menu_up = true;
-                            sound_system.play_sound("win")
-                        }
-                        system::Message::Fire => sound_system.play_sound("fire"),
-                        system::Message::Bounce => sound_system.play_sound("bounce"),
-                        system::Message::Drop => sound_system.play_sound("fail"),
-                    }
+            // Scenes see each frame's presses once, then they're forgotten.
+            gamepads.update(&mut controller);
+            if controller.has_edges() {
+                if !scenes.input(ctx!()) {
+                    *control_flow = ControlFlow::Exit;
                 }
+                controller.clear_edges();
+            }
+            if !scenes.update(ctx!()) {
+                *control_flow = ControlFlow::Exit;
+            }
+            let now = Instant::now();
+            sound_system.play_music(scenes.music(&game));
+            sound_system.update((now - last_frame).as_secs_f32());
+            last_frame = now;
+            let wanted = if scenes.captures_mouse() { settings.mouse.mode } else { MouseMode::Off };
+            if wanted != cursor_mode {
+                capture_cursor(wanted, &window);
+                cursor_mode = wanted;
             }
-            
+
             match surface.get_current_texture() {
                 Ok(tex) => {
-                    let mesh = if menu_up {
-                        menu.layout(&device, &settings)
-                    } else {
-                        box_renderer.mesh_from_state(&device, &game_state, &texture_atlas)
-                    };
+                    let meshes = scenes.render(
+                        &game,
+                        &Gfx {
+                            device: &device,
+                            atlas: &texture_atlas,
+                            boxes: &box_renderer,
+                            font: &font,
+                            hud: &hud,
+                            settings: &settings,
+                        },
+                    );
                     let view = tex
                         .texture
                         .create_view(&wgpu::TextureViewDescriptor::default());
@@ -215,7 +258,9 @@ async fn run() -> Result<(), anyhow::Error> {
                             })],
                             depth_stencil_attachment: None,
                         });
-                        box_renderer.draw_mesh(&mut pass, &mesh);
+                        for mesh in &meshes {
+                            box_renderer.draw_mesh(&mut pass, mesh);
+                        }
                     }
                     queue.submit(Some(encoder.finish()));
                     tex.present();
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+
+use brick_breaker::input;
+
 use crate::{
-    input,
     render::{self, Sprite},
     Settings,
 };
@@ -7,12 +10,15 @@ use crate::{
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Focus {
     Start,
+    Continue,
+    Settings,
     Exit,
     Fullscreen,
 }
 
 pub struct Menu {
     focus: Focus,
+    can_continue: bool,
     title: Sprite,
     start: Button,
     exit: Button,
@@ -42,6 +48,7 @@ impl Menu {
 
         Self {
             focus: Focus::Start,
+            can_continue: false,
             atlas_size,
             screen_size,
             title,
@@ -51,21 +58,31 @@ impl Menu {
         }
     }
 
+    /// Shows or hides the "Continue" option.
+    pub fn set_can_continue(&mut self, can_continue: bool) {
+        self.can_continue = can_continue;
+        if !can_continue && self.focus == Focus::Continue {
+            self.focus = Focus::Start;
+        }
+    }
+
+    fn focus_order(&self) -> &'static [Focus] {
+        if self.can_continue {
+            &[Focus::Start, Focus::Continue, Focus::Settings, Focus::Exit, Focus::Fullscreen]
+        } else {
+            &[Focus::Start, Focus::Settings, Focus::Exit, Focus::Fullscreen]
+        }
+    }
+
     pub fn input(&mut self, controller: &input::Controller, messages: &mut Vec<Message>) {
         let old_focus = self.focus;
+        let order = self.focus_order();
+        let i = order.iter().position(|f| *f == self.focus).unwrap_or(0);
         if controller.down_just_pressed() {
-            self.focus = match self.focus {
-                Focus::Start => Focus::Exit,
-                Focus::Exit => Focus::Fullscreen,
-                Focus::Fullscreen => Focus::Start,
-            }
+            self.focus = order[(i + 1) % order.len()];
         }
         if controller.up_just_pressed() {
-            self.focus = match self.focus {
-                Focus::Start => Focus::Fullscreen,
-                Focus::Exit => Focus::Start,
-                Focus::Fullscreen => Focus::Exit,
-            }
+            self.focus = order[(i + order.len() - 1) % order.len()];
         }
 
         if old_focus != self.focus {
@@ -75,18 +92,20 @@ impl Menu {
         if controller.fire_just_pressed() {
             match self.focus {
                 Focus::Start => messages.push(Message::Start),
+                Focus::Continue => messages.push(Message::Continue),
+                Focus::Settings => messages.push(Message::Settings),
                 Focus::Exit => messages.push(Message::Exit),
                 Focus::Fullscreen => messages.push(Message::ToggleFullscreen),
             }
         }
     }
 
-    pub fn layout(&self, device: &wgpu::Device, settings: &Settings) -> render::Mesh {
+    pub fn layout(&self, device: &wgpu::Device, font: &Font, settings: &Settings) -> render::Mesh {
         let padding = 4.0;
         let mut layout =
             TopDownLayout::new(glam::vec2(padding, self.screen_size.y - padding), padding);
 
-        let data = vec![
+        let mut data = vec![
             (layout.place(self.title.size), &self.title),
             (
                 layout.place_with_offset_x(self.start.selected.size, padding),
@@ -96,6 +115,14 @@ impl Menu {
                     &self.start.unselected
                 },
             ),
+        ];
+        if self.can_continue {
+            let pos = layout.place_with_offset_x(glam::vec2(0.0, font.line_height()), padding * 1.5);
+            font.place("CONTINUE", pos, self.focus == Focus::Continue, &mut data);
+        }
+        let pos = layout.place_with_offset_x(glam::vec2(0.0, font.line_height()), padding * 1.5);
+        font.place("SETTINGS", pos, self.focus == Focus::Settings, &mut data);
+        data.extend([
             (
                 layout.place_with_offset_x(self.exit.selected.size, padding),
                 if self.focus == Focus::Exit {
@@ -120,7 +147,7 @@ impl Menu {
                     &self.fullscreen.unchecked
                 }
             )
-        ];
+        ]);
 
         render::Mesh::from_sprites_with_positions(device, self.atlas_size, &data)
     }
@@ -158,11 +185,213 @@ impl Toggle {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Message {
     Start,
+    Continue,
     Exit,
     ToggleFullscreen,
     FocusChanged,
+    MainMenu,
+    Resume,
+    RestartLevel,
+    Settings,
+    Back,
+    MouseMode,
+    Sensitivity,
+    Controls,
+    Rebind(input::Button),
+    /// Switches which kind of input the controls screen lists.
+    ShowDevice,
+    ResetControls,
+    Audio,
+    MasterVolume,
+    SfxVolume,
+    MusicVolume,
+    Mute,
+}
+
+/// A menu built from text rather than dedicated button sprites. Each item
+/// sends its message when picked.
+pub struct TextMenu {
+    title: String,
+    items: Vec<(String, Message)>,
+    focus: usize,
+    screen_size: glam::Vec2,
+}
+
+impl TextMenu {
+    pub fn new(title: &str, items: &[(&str, Message)], screen_size: glam::Vec2) -> Self {
+        Self {
+            title: title.to_string(),
+            items: items
+                .iter()
+                .map(|(label, msg)| (label.to_string(), *msg))
+                .collect(),
+            focus: 0,
+            screen_size,
+        }
+    }
+
+    /// The message the highlighted item sends.
+    pub fn focused(&self) -> Message {
+        self.items[self.focus].1
+    }
+
+    /// Relabels the item that sends `msg`, e.g. to show a setting's value.
+    pub fn set_label(&mut self, msg: Message, label: String) {
+        if let Some(item) = self.items.iter_mut().find(|(_, m)| *m == msg) {
+            item.0 = label;
+        }
+    }
+
+    pub fn input(&mut self, controller: &input::Controller, messages: &mut Vec<Message>) {
+        let old_focus = self.focus;
+        if controller.down_just_pressed() {
+            self.focus = (self.focus + 1) % self.items.len();
+        }
+        if controller.up_just_pressed() {
+            self.focus = (self.focus + self.items.len() - 1) % self.items.len();
+        }
+
+        if old_focus != self.focus {
+            messages.push(Message::FocusChanged);
+        }
+
+        if controller.fire_just_pressed() {
+            messages.push(self.items[self.focus].1);
+        }
+    }
+
+    pub fn layout(&self, device: &wgpu::Device, font: &Font, atlas_size: glam::Vec2) -> render::Mesh {
+        let margin = 8.0;
+        let lines = self.items.len() as f32 + 1.0;
+        // Squeeze the lines together if there are too many to fit.
+        let padding = ((self.screen_size.y - margin - lines * font.line_height()) / (lines - 1.0))
+            .floor()
+            .clamp(1.0, 4.0);
+        let mut layout = TopDownLayout::new(glam::vec2(0.0, self.screen_size.y - margin), padding);
+
+        let mut data = Vec::new();
+        let line = glam::vec2(0.0, font.line_height());
+        let pos = layout.place(line);
+        font.place(&self.title, self.centered(font, &self.title, pos), false, &mut data);
+        for (i, (label, _)) in self.items.iter().enumerate() {
+            let pos = layout.place(line);
+            font.place(label, self.centered(font, label, pos), i == self.focus, &mut data);
+        }
+
+        render::Mesh::from_sprites_with_positions(device, atlas_size, &data)
+    }
+
+    fn centered(&self, font: &Font, text: &str, pos: glam::Vec2) -> glam::Vec2 {
+        glam::vec2(((self.screen_size.x - font.width(text)) * 0.5).floor(), pos.y)
+    }
+}
+
+/// A title card shown for a short while, e.g. between levels. Firing skips
+/// it.
+pub struct Intermission {
+    lines: Vec<String>,
+    remaining: f32,
+    screen_size: glam::Vec2,
+}
+
+impl Intermission {
+    pub fn new(lines: Vec<String>, duration: f32, screen_size: glam::Vec2) -> Self {
+        Self {
+            lines,
+            remaining: duration,
+            screen_size,
+        }
+    }
+
+    pub fn input(&mut self, controller: &input::Controller) {
+        if controller.fire_just_pressed() {
+            self.remaining = 0.0;
+        }
+    }
+
+    /// Counts down by `dt` seconds. Returns `true` once the card is done.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.remaining -= dt;
+        self.remaining <= 0.0
+    }
+
+    pub fn layout(&self, device: &wgpu::Device, font: &Font, atlas_size: glam::Vec2) -> render::Mesh {
+        let padding = 4.0;
+        let height = self.lines.len() as f32 * (font.line_height() + padding) - padding;
+        let mut layout = TopDownLayout::new(
+            glam::vec2(0.0, ((self.screen_size.y + height) * 0.5).floor()),
+            padding,
+        );
+
+        let mut data = Vec::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            let pos = layout.place(glam::vec2(0.0, font.line_height()));
+            let x = ((self.screen_size.x - font.width(line)) * 0.5).floor();
+            font.place(line, glam::vec2(x, pos.y), i == 0, &mut data);
+        }
+
+        render::Mesh::from_sprites_with_positions(device, atlas_size, &data)
+    }
+}
+
+/// Pixel font made from the `glyph_*` sprites in the atlas. Every glyph has a
+/// plain and a highlighted variant used to show focus.
+pub struct Font {
+    glyphs: HashMap<char, (Sprite, Sprite)>,
+    glyph_size: glam::Vec2,
+    spacing: f32,
+}
+
+impl Font {
+    pub fn new(atlas: &render::TextureAtlas) -> Self {
+        let glyphs = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789:-./%><+x?"
+            .chars()
+            .map(|c| {
+                let plain = *atlas.get_sprite(&format!("glyph_{}", c)).unwrap();
+                let selected = *atlas.get_sprite(&format!("glyph_{}_selected", c)).unwrap();
+                (c, (plain, selected))
+            })
+            .collect();
+        Self {
+            glyphs,
+            glyph_size: glam::vec2(3.0, 5.0),
+            spacing: 1.0,
+        }
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.glyph_size.y
+    }
+
+    pub fn width(&self, text: &str) -> f32 {
+        let n = text.chars().count() as f32;
+        (n * (self.glyph_size.x + self.spacing) - self.spacing).max(0.0)
+    }
+
+    /// Lays out `text` with its bottom left corner at `pos`. Characters
+    /// without a glyph are rendered as blanks.
+    pub fn place<'a>(
+        &'a self,
+        text: &str,
+        pos: glam::Vec2,
+        selected: bool,
+        out: &mut Vec<(glam::Vec2, &'a Sprite)>,
+    ) {
+        let mut cursor = pos;
+        for c in text.chars() {
+            let glyph = self
+                .glyphs
+                .get(&c)
+                .or_else(|| self.glyphs.get(&c.to_ascii_uppercase()));
+            if let Some((plain, highlighted)) = glyph {
+                out.push((cursor, if selected { highlighted } else { plain }));
+            }
+            cursor.x += self.glyph_size.x + self.spacing;
+        }
+    }
 }
 
 pub struct TopDownLayout {
@@ -0,0 +1,61 @@
+use crate::state::Body;
+
+pub const CAPSULE_SIZE: glam::Vec2 = glam::vec2(7.0, 5.0);
+pub const BOLT_SIZE: glam::Vec2 = glam::vec2(1.0, 3.0);
+/// How fast capsules fall, in arena units per second.
+pub const CAPSULE_SPEED: f32 = 15.0;
+/// How much wider the paddle gets with `WidePaddle`.
+pub const WIDE_FACTOR: f32 = 1.5;
+/// Ball speed multiplier while `SlowBall` is active.
+pub const SLOW_FACTOR: f32 = 0.6;
+/// Angle between the new balls spawned by `MultiBall` and the original.
+pub const MULTI_BALL_SPREAD: f32 = 0.5;
+/// How fast laser bolts travel, in arena units per second.
+pub const BOLT_SPEED: f32 = 60.0;
+/// Seconds between laser shots.
+pub const LASER_COOLDOWN: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    WidePaddle,
+    SlowBall,
+    MultiBall,
+    StickyPaddle,
+    Laser,
+    ExtraLife,
+}
+
+impl PowerUpKind {
+    pub const ALL: [Self; 6] = [
+        Self::WidePaddle,
+        Self::SlowBall,
+        Self::MultiBall,
+        Self::StickyPaddle,
+        Self::Laser,
+        Self::ExtraLife,
+    ];
+
+    /// How long the effect lasts in seconds, or `None` if it happens once
+    /// when collected.
+    pub fn duration(self) -> Option<f32> {
+        match self {
+            Self::WidePaddle | Self::StickyPaddle => Some(15.0),
+            Self::SlowBall | Self::Laser => Some(10.0),
+            Self::MultiBall | Self::ExtraLife => None,
+        }
+    }
+}
+
+/// A capsule falling from a destroyed brick, waiting to be caught.
+#[derive(Debug, Clone, Copy)]
+pub struct PowerUp {
+    pub body: Body,
+    pub kind: PowerUpKind,
+}
+
+/// A timed power-up currently affecting the game.
+#[derive(Debug, Clone, Copy)]
+pub struct Effect {
+    pub kind: PowerUpKind,
+    pub remaining: f32,
+}
@@ -3,7 +3,10 @@ use std::{collections::HashMap, mem::size_of};
 use image::EncodableLayout;
 use wgpu::util::DeviceExt;
 
-use crate::state::State;
+use brick_breaker::{
+    powerup::PowerUpKind,
+    state::{Brick, BrickKind, State},
+};
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
@@ -147,13 +150,15 @@ impl BoxRenderer {
         })
     }
 
-    pub fn mesh_from_state(&self, device: &wgpu::Device, state: &State, texture_atlas: &TextureAtlas) -> Mesh {
+    /// Builds the mesh for the game objects. `alpha` blends moving objects
+    /// between their previous and current positions.
+    pub fn mesh_from_state(&self, device: &wgpu::Device, state: &State, texture_atlas: &TextureAtlas, alpha: f32) -> Mesh {
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
 
         let mut index = 0;
 
-        let min = state.player.body.pos;
+        let min = state.player.lerp_pos(alpha);
         let max = min + state.player.body.size;
         let sprite = texture_atlas.get_sprite("player").unwrap();
         let scale = glam::vec2(1.0 / texture_atlas.width() as f32, 1.0 / texture_atlas.height() as f32);
@@ -173,31 +178,39 @@ impl BoxRenderer {
         indices.push(index + 3);
         index += 4;
 
-        let min = state.ball.body.pos;
-        let max = min + state.ball.body.size;
         let sprite = texture_atlas.get_sprite("ball").unwrap();
         let uv_min = sprite.min * scale;
         let uv_max = (sprite.min + sprite.size) * scale;
+        for ball in &state.balls {
+            let min = ball.lerp_pos(alpha);
+            let max = min + ball.body.size;
 
-        vertices.push(BoxVertex { position: min, uv: uv_min });
-        vertices.push(BoxVertex { position: glam::vec2(max.x, min.y), uv: glam::vec2(uv_max.x, uv_min.y) });
-        vertices.push(BoxVertex { position: max, uv: uv_max });
-        vertices.push(BoxVertex { position: glam::vec2(min.x, max.y), uv: glam::vec2(uv_min.x, uv_max.y) });
+            vertices.push(BoxVertex { position: min, uv: uv_min });
+            vertices.push(BoxVertex { position: glam::vec2(max.x, min.y), uv: glam::vec2(uv_max.x, uv_min.y) });
+            vertices.push(BoxVertex { position: max, uv: uv_max });
+            vertices.push(BoxVertex { position: glam::vec2(min.x, max.y), uv: glam::vec2(uv_min.x, uv_max.y) });
 
-        indices.push(index);
-        indices.push(index + 1);
-        indices.push(index + 2);
-        indices.push(index);
-        indices.push(index + 2);
-        indices.push(index + 3);
-        index += 4;
+            indices.push(index);
+            indices.push(index + 1);
+            indices.push(index + 2);
+            indices.push(index);
+            indices.push(index + 2);
+            indices.push(index + 3);
+            index += 4;
+        }
 
 
         for brick in &state.bricks {
             let body = &brick.body; 
             let min = body.pos;
             let max = min + body.size;
-            let sprite_id = format!("brick{}", brick.status);
+            let sprite_id = match brick.kind {
+                BrickKind::Normal => format!("brick{}", brick.status),
+                BrickKind::Indestructible => "brick_steel".to_string(),
+                BrickKind::Explosive => "brick_explosive".to_string(),
+                BrickKind::Regenerating => worn_sprite("brick_regen", brick),
+                BrickKind::Reinforced => worn_sprite("brick_reinforced", brick),
+            };
             if let Some(sprite) = texture_atlas.get_sprite(&sprite_id) {
                 let uv_min = sprite.min * scale;
                 let uv_max = (sprite.min + sprite.size) * scale;
@@ -217,10 +230,44 @@ impl BoxRenderer {
             }
         }
 
+        let capsules = state.power_ups.iter().map(|p| {
+            let sprite_id = match p.kind {
+                PowerUpKind::WidePaddle => "powerup_wide",
+                PowerUpKind::SlowBall => "powerup_slow",
+                PowerUpKind::MultiBall => "powerup_multi_ball",
+                PowerUpKind::StickyPaddle => "powerup_sticky",
+                PowerUpKind::Laser => "powerup_laser",
+                PowerUpKind::ExtraLife => "powerup_extra_life",
+            };
+            (&p.body, sprite_id)
+        });
+        let bolts = state.bolts.iter().map(|b| (b, "laser_bolt"));
+        for (body, sprite_id) in capsules.chain(bolts) {
+            let min = body.pos;
+            let max = min + body.size;
+            if let Some(sprite) = texture_atlas.get_sprite(sprite_id) {
+                let uv_min = sprite.min * scale;
+                let uv_max = (sprite.min + sprite.size) * scale;
+
+                vertices.push(BoxVertex { position: min, uv: uv_min });
+                vertices.push(BoxVertex { position: glam::vec2(max.x, min.y), uv: glam::vec2(uv_max.x, uv_min.y) });
+                vertices.push(BoxVertex { position: max, uv: uv_max });
+                vertices.push(BoxVertex { position: glam::vec2(min.x, max.y), uv: glam::vec2(uv_min.x, uv_max.y) });
+
+                indices.push(index);
+                indices.push(index + 1);
+                indices.push(index + 2);
+                indices.push(index);
+                indices.push(index + 2);
+                indices.push(index + 3);
+                index += 4;
+            }
+        }
+
         Mesh::from_verts_and_indices(device, vertices, indices)
     }
 
-    pub fn draw_mesh<'a, 'b>(&'a self, pass: &'b mut wgpu::RenderPass<'a>, mesh: &'a Mesh) {
+    pub fn draw_mesh<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, mesh: &'a Mesh) {
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, &self.resources, &[]);
         pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
@@ -229,6 +276,18 @@ impl BoxRenderer {
     }
 }
 
+/// Picks one of a brick's sprites by how worn down it is: whole at full
+/// strength, broken with one hit left and cracked in between.
+fn worn_sprite(name: &str, brick: &Brick) -> String {
+    if brick.status >= brick.max_status {
+        name.to_string()
+    } else if brick.status <= 1 {
+        format!("{}_broken", name)
+    } else {
+        format!("{}_cracked", name)
+    }
+}
+
 pub struct Mesh {
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
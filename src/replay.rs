@@ -0,0 +1,369 @@
+use std::path::Path;
+
+use crate::{
+    campaign::Campaign,
+    input::TickInput,
+    level::LevelError,
+    score::Score,
+    state::{State, STARTING_LIVES},
+    system::{Message, MovementSystem, PADDLE_SPEED},
+    timestep::SIM_STEP,
+};
+
+const MAGIC: &[u8; 4] = b"BRKR";
+const VERSION: u8 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("not a replay file")]
+    NotAReplay,
+    #[error("replay format version {0} isn't supported")]
+    UnsupportedVersion(u8),
+    #[error("replay file is truncated")]
+    Truncated,
+    #[error("replay starts on level {0}, which isn't in the campaign")]
+    UnknownLevel(usize),
+    #[error(transparent)]
+    Level(#[from] LevelError),
+}
+
+/// A recorded game: the seed, level, lives and score it started from plus
+/// the player's input for every simulation tick. Only the ticks where the
+/// input changed are stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub level: usize,
+    /// Lives at the start, which are fewer than a new game's when a level
+    /// is restarted.
+    pub lives: u32,
+    pub score: Score,
+    pub arena_size: glam::Vec2,
+    pub player_size: glam::Vec2,
+    pub ball_size: glam::Vec2,
+    pub brick_size: glam::Vec2,
+    /// Number of ticks simulated.
+    pub ticks: u64,
+    /// [`fingerprint`] of the state when recording finished.
+    pub fingerprint: u64,
+    changes: Vec<(u64, TickInput)>,
+}
+
+impl Replay {
+    /// Starts recording a game that was just set up on `state` with `seed`,
+    /// starting at campaign `level`.
+    pub fn new(seed: u64, level: usize, state: &State) -> Self {
+        Self {
+            seed,
+            level,
+            lives: state.lives,
+            score: state.score,
+            arena_size: state.arena_size,
+            player_size: state.player.body.size,
+            ball_size: state.ball_size,
+            brick_size: state.brick_size,
+            ticks: 0,
+            fingerprint: 0,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Records the input used for the next tick.
+    pub fn record(&mut self, input: TickInput) {
+        let current = self.changes.last().map(|&(_, i)| i).unwrap_or_default();
+        if input != current {
+            self.changes.push((self.ticks, input));
+        }
+        self.ticks += 1;
+    }
+
+    /// Stops recording, remembering what `state` ended up as so playback can
+    /// be checked against it.
+    pub fn finish(&mut self, state: &State) {
+        self.fingerprint = fingerprint(state);
+    }
+
+    /// The input for every recorded tick, in order.
+    pub fn inputs(&self) -> impl Iterator<Item = TickInput> + '_ {
+        let mut changes = self.changes.iter().peekable();
+        let mut current = TickInput::default();
+        (0..self.ticks).map(move |tick| {
+            if let Some(&(_, input)) = changes.next_if(|(t, _)| *t == tick) {
+                current = input;
+            }
+            current
+        })
+    }
+
+    /// Replays the game from the start, moving through the campaign the
+    /// same way the game does, and returns the final state.
+    pub fn play(&self, campaign: &Campaign) -> Result<State, ReplayError> {
+        let mut state = State::new(self.arena_size, self.player_size, self.ball_size, self.brick_size);
+        let mut level = self.level;
+        let first = campaign.levels.get(level).ok_or(ReplayError::UnknownLevel(level))?;
+        state.lives = self.lives;
+        state.score = self.score;
+        state.reseed(self.seed);
+        state.load_level(first)?;
+
+        let mut movement = MovementSystem::new(PADDLE_SPEED);
+        let mut messages = Vec::new();
+        for input in self.inputs() {
+            movement.input(&input);
+            movement.update(&mut state, SIM_STEP, &mut messages);
+
+            if messages.iter().any(|m| matches!(m, Message::GameOver)) {
+                break;
+            }
+            if messages.iter().any(|m| matches!(m, Message::Win)) {
+                level += 1;
+                match campaign.levels.get(level) {
+                    Some(next) => state.load_level(next)?,
+                    None => break,
+                }
+            }
+            messages.clear();
+        }
+        Ok(state)
+    }
+
+    /// Whether `state` is exactly where the recording ended.
+    pub fn matches(&self, state: &State) -> bool {
+        fingerprint(state) == self.fingerprint
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        Ok(std::fs::write(path, self.to_bytes())?)
+    }
+
+    /// Little endian throughout. Each input change is stored as the number of
+    /// ticks since the previous change, the direction and the fire flag.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 + self.changes.len() * 9);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.extend_from_slice(&(self.level as u32).to_le_bytes());
+        for v in [self.lives, self.score.total, self.score.combo] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        for v in [self.arena_size, self.player_size, self.ball_size, self.brick_size] {
+            out.extend_from_slice(&v.x.to_le_bytes());
+            out.extend_from_slice(&v.y.to_le_bytes());
+        }
+        out.extend_from_slice(&self.ticks.to_le_bytes());
+        out.extend_from_slice(&self.fingerprint.to_le_bytes());
+        out.extend_from_slice(&(self.changes.len() as u32).to_le_bytes());
+        let mut last = 0;
+        for &(tick, input) in &self.changes {
+            out.extend_from_slice(&((tick - last) as u32).to_le_bytes());
+            out.extend_from_slice(&input.dir.to_le_bytes());
+            out.push(input.fire as u8);
+            last = tick;
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = Reader(bytes);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(ReplayError::NotAReplay);
+        }
+        let version = reader.take(1)?[0];
+        if version != 1 && version != VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let seed = reader.u64()?;
+        let level = reader.u32()? as usize;
+        // Version 1 replays always started a new game.
+        let (lives, score) = if version == 1 {
+            (STARTING_LIVES, Score::default())
+        } else {
+            let lives = reader.u32()?;
+            let (total, combo) = (reader.u32()?, reader.u32()?);
+            (lives, Score { total, combo })
+        };
+        let arena_size = reader.vec2()?;
+        let player_size = reader.vec2()?;
+        let ball_size = reader.vec2()?;
+        let brick_size = reader.vec2()?;
+        let ticks = reader.u64()?;
+        let fingerprint = reader.u64()?;
+        let count = reader.u32()?;
+        let mut changes = Vec::new();
+        let mut tick = 0;
+        for _ in 0..count {
+            tick += reader.u32()? as u64;
+            let dir = reader.f32()?;
+            let fire = reader.take(1)?[0] != 0;
+            changes.push((tick, TickInput { dir, fire }));
+        }
+        Ok(Self {
+            seed,
+            level,
+            lives,
+            score,
+            arena_size,
+            player_size,
+            ball_size,
+            brick_size,
+            ticks,
+            fingerprint,
+            changes,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ReplayError> {
+        if self.0.len() < n {
+            return Err(ReplayError::Truncated);
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, ReplayError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ReplayError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, ReplayError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn vec2(&mut self) -> Result<glam::Vec2, ReplayError> {
+        Ok(glam::vec2(self.f32()?, self.f32()?))
+    }
+}
+
+/// A hash of everything in `state` that changes during play. Floats are
+/// hashed bit for bit, so any divergence at all shows up.
+pub fn fingerprint(state: &State) -> u64 {
+    // FNV-1a, which unlike `DefaultHasher` is the same on every build.
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    let mut feed_vec = |v: glam::Vec2| {
+        feed(&v.x.to_bits().to_le_bytes());
+        feed(&v.y.to_bits().to_le_bytes());
+    };
+
+    feed_vec(state.player.body.pos);
+    feed_vec(state.player.body.size);
+    for ball in &state.balls {
+        feed_vec(ball.body.pos);
+        feed_vec(ball.vel);
+    }
+    for brick in &state.bricks {
+        feed_vec(brick.body.pos);
+        feed_vec(glam::vec2(brick.status as f32, brick.regen_timer));
+    }
+    for power_up in &state.power_ups {
+        feed_vec(power_up.body.pos);
+    }
+    for bolt in &state.bolts {
+        feed_vec(bolt.pos);
+    }
+    for effect in &state.effects {
+        feed_vec(glam::vec2(effect.kind as u8 as f32, effect.remaining));
+    }
+    feed_vec(glam::vec2(state.lives as f32, state.score.total as f32));
+    feed_vec(glam::vec2(state.score.combo as f32, state.laser_cooldown));
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+
+    fn campaign() -> Campaign {
+        let level = Level::from_ron(
+            r#"Level(
+                name: "Test",
+                drop_rate: 0.5,
+                legend: { '1': (hits: 1), '2': (hits: 2) },
+                grid: ["1212121212", "2121212121"],
+            )"#,
+        )
+        .unwrap();
+        Campaign { levels: vec![level] }
+    }
+
+    /// Plays a scripted game, recording it along the way. It starts with
+    /// `lives` and `score` as if a level was restarted.
+    fn record(seed: u64, lives: u32, score: u32) -> (Replay, State) {
+        let campaign = campaign();
+        let mut state = State::new(
+            glam::vec2(80.0, 74.0),
+            glam::vec2(16.0, 3.0),
+            glam::vec2(4.0, 4.0),
+            glam::vec2(8.0, 4.0),
+        );
+        state.new_game();
+        state.lives = lives;
+        state.score.total = score;
+        state.reseed(seed);
+        state.load_level(&campaign.levels[0]).unwrap();
+        let mut replay = Replay::new(seed, 0, &state);
+
+        let mut movement = MovementSystem::new(PADDLE_SPEED);
+        let mut messages = Vec::new();
+        for tick in 0..3000 {
+            let input = TickInput {
+                dir: ((tick / 40) % 3) as f32 - 1.0,
+                fire: tick % 97 == 5,
+            };
+            movement.input(&input);
+            replay.record(movement.last_input());
+            movement.update(&mut state, SIM_STEP, &mut messages);
+            let done = messages.iter().any(|m| matches!(m, Message::Win | Message::GameOver));
+            messages.clear();
+            if done {
+                break;
+            }
+        }
+        replay.finish(&state);
+        (replay, state)
+    }
+
+    #[test]
+    fn playback_reproduces_the_final_state() {
+        for (lives, score) in [(STARTING_LIVES, 0), (1, 500)] {
+            let (replay, recorded) = record(42, lives, score);
+            let played = replay.play(&campaign()).unwrap();
+            assert!(replay.matches(&played));
+            assert_eq!(played.score.total, recorded.score.total);
+            assert_eq!(played.lives, recorded.lives);
+        }
+    }
+
+    #[test]
+    fn replay_survives_a_round_trip() {
+        let (replay, _) = record(7, 2, 120);
+        let bytes = replay.to_bytes();
+        assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+        assert!(matches!(
+            Replay::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ReplayError::Truncated)
+        ));
+        assert!(matches!(Replay::from_bytes(b"nope"), Err(ReplayError::NotAReplay)));
+    }
+}
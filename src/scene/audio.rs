@@ -0,0 +1,132 @@
+use brick_breaker::input::Button;
+
+use crate::{
+    game::Game,
+    menu::{self, TextMenu},
+    render::Mesh,
+    sound::AudioSettings,
+};
+
+use super::{Command, Context, Gfx, Scene};
+
+/// How much left, right or fire changes a volume by.
+const VOLUME_STEP: f32 = 0.1;
+
+/// Volume sliders. Left and right adjust the highlighted one, fire steps it
+/// up and wraps around, and every change plays a sound at the new volume.
+pub struct AudioScene {
+    menu: TextMenu,
+    messages: Vec<menu::Message>,
+}
+
+impl AudioScene {
+    pub fn new(screen_size: glam::Vec2) -> Self {
+        Self {
+            menu: TextMenu::new(
+                "AUDIO",
+                &[
+                    ("MASTER", menu::Message::MasterVolume),
+                    ("SFX", menu::Message::SfxVolume),
+                    ("MUSIC", menu::Message::MusicVolume),
+                    ("MUTE", menu::Message::Mute),
+                    ("BACK", menu::Message::Back),
+                ],
+                screen_size,
+            ),
+            messages: Vec::new(),
+        }
+    }
+
+    fn show(&mut self, audio: &AudioSettings) {
+        let percent = |v: f32| format!("{}%", (v * 100.0).round());
+        self.menu.set_label(menu::Message::MasterVolume, format!("MASTER: {}", percent(audio.master)));
+        self.menu.set_label(menu::Message::SfxVolume, format!("SFX: {}", percent(audio.sfx)));
+        self.menu.set_label(menu::Message::MusicVolume, format!("MUSIC: {}", percent(audio.music)));
+        self.menu.set_label(
+            menu::Message::Mute,
+            format!("MUTE: {}", if audio.muted { "ON" } else { "OFF" }),
+        );
+    }
+}
+
+/// The volume `msg` adjusts, if it's one of the sliders.
+fn slider(audio: &mut AudioSettings, msg: menu::Message) -> Option<&mut f32> {
+    match msg {
+        menu::Message::MasterVolume => Some(&mut audio.master),
+        menu::Message::SfxVolume => Some(&mut audio.sfx),
+        menu::Message::MusicVolume => Some(&mut audio.music),
+        _ => None,
+    }
+}
+
+fn step(volume: &mut f32, by: f32) {
+    // Round so repeated steps land on whole percentages.
+    *volume = ((*volume + by) * 10.0).round().clamp(0.0, 10.0) / 10.0;
+}
+
+impl Scene for AudioScene {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.show(&ctx.settings.audio);
+    }
+
+    fn input(&mut self, ctx: &mut Context) -> Command {
+        if ctx.controller.back_just_pressed() {
+            return Command::Pop;
+        }
+        let audio = &mut ctx.settings.audio;
+        let mut changed = false;
+
+        let nudge = if ctx.controller.just_pressed(Button::Left) {
+            -VOLUME_STEP
+        } else if ctx.controller.just_pressed(Button::Right) {
+            VOLUME_STEP
+        } else {
+            0.0
+        };
+        if nudge != 0.0 {
+            if let Some(volume) = slider(audio, self.menu.focused()) {
+                step(volume, nudge);
+                changed = true;
+            }
+        }
+
+        self.menu.input(ctx.controller, &mut self.messages);
+        let mut command = Command::None;
+        for msg in self.messages.drain(..) {
+            match msg {
+                menu::Message::Mute => {
+                    audio.muted = !audio.muted;
+                    changed = true;
+                }
+                menu::Message::Back => command = Command::Pop,
+                menu::Message::FocusChanged => ctx.sound.play_sound("bounce"),
+                msg => {
+                    if let Some(volume) = slider(audio, msg) {
+                        if *volume >= 1.0 {
+                            *volume = 0.0;
+                        } else {
+                            step(volume, VOLUME_STEP);
+                        }
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if changed {
+            ctx.sound.settings = *audio;
+            // Let the player hear the new volume.
+            ctx.sound.play_sound("bounce");
+            self.show(audio);
+        }
+        command
+    }
+
+    fn render(&self, _game: &Game, gfx: &Gfx, meshes: &mut Vec<Mesh>) {
+        meshes.push(self.menu.layout(gfx.device, gfx.font, gfx.atlas.size()));
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
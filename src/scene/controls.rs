@@ -0,0 +1,223 @@
+use brick_breaker::{
+    bindings::{Binding, Bindings},
+    input::{Button, Controller},
+};
+
+use crate::{
+    game::Game,
+    menu::{self, TextMenu},
+    render::Mesh,
+};
+
+use super::{Command, Context, Gfx, Scene};
+
+/// Lists the inputs bound to each action, one kind of input at a time.
+/// Picking an action while keys are listed waits for the next key pressed
+/// and binds that instead; Escape cancels. Mouse and gamepad bindings can
+/// only be changed in the settings file.
+pub struct ControlsScene {
+    menu: TextMenu,
+    messages: Vec<menu::Message>,
+    /// The action waiting for a key, if any.
+    capturing: Option<Button>,
+    showing: Device,
+}
+
+/// A kind of input, for picking which bindings to list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Device {
+    Keys,
+    Mouse,
+    Gamepad,
+}
+
+impl Device {
+    fn next(self) -> Self {
+        match self {
+            Self::Keys => Self::Mouse,
+            Self::Mouse => Self::Gamepad,
+            Self::Gamepad => Self::Keys,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Keys => "KEYS",
+            Self::Mouse => "MOUSE",
+            Self::Gamepad => "GAMEPAD",
+        }
+    }
+
+    fn has(self, binding: &Binding) -> bool {
+        matches!(
+            (self, binding),
+            (Self::Keys, Binding::Key(_)) | (Self::Mouse, Binding::Mouse(_)) | (Self::Gamepad, Binding::Gamepad(_))
+        )
+    }
+}
+
+impl ControlsScene {
+    pub fn new(screen_size: glam::Vec2) -> Self {
+        let mut items: Vec<_> = Button::ALL
+            .iter()
+            .map(|&b| (action_name(b), menu::Message::Rebind(b)))
+            .collect();
+        items.push(("SHOWING", menu::Message::ShowDevice));
+        items.push(("RESET DEFAULTS", menu::Message::ResetControls));
+        items.push(("BACK", menu::Message::Back));
+        Self {
+            menu: TextMenu::new("CONTROLS", &items, screen_size),
+            messages: Vec::new(),
+            capturing: None,
+            showing: Device::Keys,
+        }
+    }
+
+    fn show(&mut self, bindings: &Bindings) {
+        for button in Button::ALL {
+            let inputs = if self.capturing == Some(button) {
+                "?".to_string()
+            } else {
+                let labels: Vec<_> = bindings
+                    .get(button)
+                    .iter()
+                    .filter(|b| self.showing.has(b))
+                    .map(Binding::label)
+                    .collect();
+                if labels.is_empty() {
+                    "-".to_string()
+                } else {
+                    labels.join(" ")
+                }
+            };
+            self.menu.set_label(
+                menu::Message::Rebind(button),
+                format!("{}: {}", action_name(button), inputs),
+            );
+        }
+        self.menu
+            .set_label(menu::Message::ShowDevice, format!("SHOWING: {}", self.showing.name()));
+    }
+}
+
+/// Binds the key `controller` picked up to `button`, unless it's Escape.
+/// Returns `None` while no key has been pressed yet, and otherwise whether
+/// the bindings changed.
+fn capture_key(button: Button, controller: &Controller, bindings: &mut Bindings) -> Option<bool> {
+    let key = controller.last_key()?.clone();
+    if key == Binding::Key("Escape".to_string()) {
+        return Some(false);
+    }
+    bindings.rebind_key(button, key);
+    Some(true)
+}
+
+fn action_name(button: Button) -> &'static str {
+    match button {
+        Button::Left => "LEFT",
+        Button::Right => "RIGHT",
+        Button::Up => "UP",
+        Button::Down => "DOWN",
+        Button::Fire => "FIRE",
+        Button::Back => "PAUSE",
+    }
+}
+
+impl Scene for ControlsScene {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.show(&ctx.settings.bindings);
+    }
+
+    fn input(&mut self, ctx: &mut Context) -> Command {
+        if let Some(button) = self.capturing {
+            let Some(rebound) = capture_key(button, ctx.controller, &mut ctx.settings.bindings) else {
+                return Command::None;
+            };
+            self.capturing = None;
+            if rebound {
+                ctx.controller.set_bindings(ctx.settings.bindings.clone());
+            }
+            self.show(&ctx.settings.bindings);
+            return Command::None;
+        }
+
+        if ctx.controller.back_just_pressed() {
+            return Command::Pop;
+        }
+        self.menu.input(ctx.controller, &mut self.messages);
+
+        let mut command = Command::None;
+        for msg in self.messages.drain(..) {
+            match msg {
+                menu::Message::Rebind(button) if self.showing == Device::Keys => self.capturing = Some(button),
+                menu::Message::ShowDevice => self.showing = self.showing.next(),
+                menu::Message::ResetControls => {
+                    ctx.settings.bindings = Bindings::default();
+                    ctx.controller.set_bindings(Bindings::default());
+                }
+                menu::Message::Back => command = Command::Pop,
+                menu::Message::FocusChanged => ctx.sound.play_sound("bounce"),
+                _ => (),
+            }
+        }
+        self.show(&ctx.settings.bindings);
+        command
+    }
+
+    fn render(&self, _game: &Game, gfx: &Gfx, meshes: &mut Vec<Mesh>) {
+        meshes.push(self.menu.layout(gfx.device, gfx.font, gfx.atlas.size()));
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brick_breaker::input::Input;
+    use winit::event::VirtualKeyCode;
+
+    fn key(name: &str) -> Binding {
+        Binding::Key(name.to_string())
+    }
+
+    #[test]
+    fn captures_the_next_key_and_escape_cancels() {
+        let mut bindings = Bindings::default();
+        let mut controller = Controller::new();
+        assert_eq!(capture_key(Button::Fire, &controller, &mut bindings), None);
+
+        controller.input(&Input::KeyboardInput(VirtualKeyCode::Escape, true));
+        assert_eq!(capture_key(Button::Fire, &controller, &mut bindings), Some(false));
+        assert_eq!(bindings, Bindings::default());
+
+        controller.clear_edges();
+        controller.input(&Input::KeyboardInput(VirtualKeyCode::W, true));
+        // Only the first key counts.
+        controller.input(&Input::KeyboardInput(VirtualKeyCode::X, true));
+        assert_eq!(capture_key(Button::Fire, &controller, &mut bindings), Some(true));
+
+        // W moves over from Up, which gets Fire's old keys in return. The
+        // mouse and gamepad bindings stay put.
+        assert_eq!(bindings.get(Button::Fire)[0], key("W"));
+        assert!(bindings.get(Button::Fire).iter().any(|b| matches!(b, Binding::Mouse(0))));
+        assert!(!bindings.get(Button::Up).contains(&key("W")));
+        assert!(bindings.get(Button::Up).contains(&key("Space")));
+        bindings.validate().unwrap();
+    }
+
+    #[test]
+    fn holding_the_confirm_key_doesnt_bind_it() {
+        let mut bindings = Bindings::default();
+        let mut controller = Controller::new();
+        controller.input(&Input::KeyboardInput(VirtualKeyCode::Return, true));
+        controller.clear_edges();
+
+        // Still down from picking the action, so the key repeats.
+        controller.input(&Input::KeyboardInput(VirtualKeyCode::Return, true));
+        assert_eq!(capture_key(Button::Up, &controller, &mut bindings), None);
+        assert_eq!(bindings, Bindings::default());
+    }
+}
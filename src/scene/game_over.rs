@@ -0,0 +1,74 @@
+use crate::{
+    game::Game,
+    menu::{self, TextMenu},
+    render::Mesh,
+};
+
+use super::{Command, Context, Gfx, Playing, Scene};
+
+/// End of the game, either out of lives or through the whole campaign.
+pub struct GameOver {
+    menu: TextMenu,
+    messages: Vec<menu::Message>,
+    music: &'static str,
+}
+
+impl GameOver {
+    pub fn lost(screen_size: glam::Vec2) -> Self {
+        Self::new(
+            TextMenu::new(
+                "GAME OVER",
+                &[("RETRY", menu::Message::Start), ("MAIN MENU", menu::Message::MainMenu)],
+                screen_size,
+            ),
+            "game_over",
+        )
+    }
+
+    pub fn won(screen_size: glam::Vec2) -> Self {
+        Self::new(
+            TextMenu::new("YOU WIN", &[("MAIN MENU", menu::Message::MainMenu)], screen_size),
+            "menu",
+        )
+    }
+
+    fn new(menu: TextMenu, music: &'static str) -> Self {
+        Self {
+            menu,
+            messages: Vec::new(),
+            music,
+        }
+    }
+}
+
+impl Scene for GameOver {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        ctx.game.save_replay();
+    }
+
+    fn input(&mut self, ctx: &mut Context) -> Command {
+        self.menu.input(ctx.controller, &mut self.messages);
+
+        let mut command = Command::None;
+        for msg in self.messages.drain(..) {
+            match msg {
+                // Another go at the level that was lost, not the whole campaign.
+                menu::Message::Start if ctx.game.start(ctx.game.current_level).is_ok() => {
+                    command = Command::Replace(Box::new(Playing));
+                }
+                menu::Message::MainMenu => command = Command::MainMenu,
+                menu::Message::FocusChanged => ctx.sound.play_sound("bounce"),
+                _ => (),
+            }
+        }
+        command
+    }
+
+    fn music<'a>(&'a self, _game: &'a Game) -> Option<&'a str> {
+        Some(self.music)
+    }
+
+    fn render(&self, _game: &Game, gfx: &Gfx, meshes: &mut Vec<Mesh>) {
+        meshes.push(self.menu.layout(gfx.device, gfx.font, gfx.atlas.size()));
+    }
+}
@@ -0,0 +1,42 @@
+use crate::{game::Game, menu::Intermission, render::Mesh};
+
+use super::{Command, Context, Gfx, Scene};
+
+/// Title card for the next level. Loads it once the card is done.
+pub struct LevelComplete {
+    card: Intermission,
+}
+
+impl LevelComplete {
+    pub fn new(level: usize, name: &str, screen_size: glam::Vec2) -> Self {
+        Self {
+            card: Intermission::new(
+                vec![format!("LEVEL {}", level + 1), name.to_uppercase()],
+                2.0,
+                screen_size,
+            ),
+        }
+    }
+}
+
+impl Scene for LevelComplete {
+    fn input(&mut self, ctx: &mut Context) -> Command {
+        self.card.input(ctx.controller);
+        Command::None
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Command {
+        let game = &mut *ctx.game;
+        if !self.card.update(game.timestep.tick() as f32 * game.timestep.step()) {
+            Command::None
+        } else if game.load_current_level().is_ok() {
+            Command::Pop
+        } else {
+            Command::MainMenu
+        }
+    }
+
+    fn render(&self, _game: &Game, gfx: &Gfx, meshes: &mut Vec<Mesh>) {
+        meshes.push(self.card.layout(gfx.device, gfx.font, gfx.atlas.size()));
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    demo::Demo,
+    game::Game,
+    menu::{self, Menu},
+    render::Mesh,
+};
+
+use super::{Command, Context, Gfx, Playing, Scene, SettingsScene};
+
+/// The title screen, with the autopilot playing behind it when left idle.
+/// Always at the bottom of the stack.
+pub struct MainMenu {
+    menu: Menu,
+    demo: Demo,
+    messages: Vec<menu::Message>,
+}
+
+impl MainMenu {
+    pub fn new(menu: Menu, demo: Demo) -> Self {
+        Self {
+            menu,
+            demo,
+            messages: Vec::new(),
+        }
+    }
+}
+
+impl Scene for MainMenu {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        // However the last game ended, it's over now.
+        ctx.game.save_replay();
+        self.menu.set_can_continue(ctx.game.progress.highest_unlocked > 0);
+        self.demo.interrupt();
+    }
+
+    fn input(&mut self, ctx: &mut Context) -> Command {
+        self.demo.interrupt();
+        self.menu.input(ctx.controller, &mut self.messages);
+
+        let mut command = Command::None;
+        for msg in self.messages.drain(..) {
+            match msg {
+                menu::Message::Exit => command = Command::Quit,
+                menu::Message::Start | menu::Message::Continue => {
+                    let level = if msg == menu::Message::Continue {
+                        ctx.game.continue_level()
+                    } else {
+                        0
+                    };
+                    if ctx.game.start(level).is_ok() {
+                        command = Command::Push(Box::new(Playing));
+                    }
+                }
+                menu::Message::Settings => command = Command::Push(Box::new(SettingsScene::new(ctx.screen_size))),
+                menu::Message::ToggleFullscreen => ctx.toggle_fullscreen(),
+                menu::Message::FocusChanged => ctx.sound.play_sound("bounce"),
+                _ => (),
+            }
+        }
+        command
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Command {
+        let game = &mut *ctx.game;
+        self.demo.update(&game.campaign, game.timestep.tick(), game.timestep.step());
+        Command::None
+    }
+
+    fn music<'a>(&'a self, _game: &'a Game) -> Option<&'a str> {
+        Some("menu")
+    }
+
+    fn render(&self, game: &Game, gfx: &Gfx, meshes: &mut Vec<Mesh>) {
+        if let Some(state) = self.demo.state() {
+            meshes.push(gfx.boxes.mesh_from_state(gfx.device, state, gfx.atlas, game.timestep.alpha()));
+        }
+        meshes.push(self.menu.layout(gfx.device, gfx.font, gfx.settings));
+    }
+}
@@ -0,0 +1,203 @@
+//! The screens the game moves between, kept on a stack. Only the top scene
+//! gets input and updates; overlays like the pause menu are drawn on top of
+//! whatever is below them.
+
+mod audio;
+mod controls;
+mod game_over;
+mod level_complete;
+mod main_menu;
+mod paused;
+mod playing;
+mod settings;
+
+pub use audio::AudioScene;
+pub use controls::ControlsScene;
+pub use game_over::GameOver;
+pub use level_complete::LevelComplete;
+pub use main_menu::MainMenu;
+pub use paused::Paused;
+pub use playing::Playing;
+pub use settings::SettingsScene;
+
+use brick_breaker::input::Controller;
+
+use crate::{
+    game::Game,
+    hud::Hud,
+    menu::Font,
+    render::{BoxRenderer, Mesh, TextureAtlas},
+    sound::SoundSystem,
+    util::set_fullscreen,
+    Settings,
+};
+
+/// What a scene wants to happen next. Scenes never touch the stack directly.
+pub enum Command {
+    None,
+    Push(Box<dyn Scene>),
+    Pop,
+    /// Swaps the top scene for another.
+    Replace(Box<dyn Scene>),
+    /// Pops everything above the main menu.
+    MainMenu,
+    Quit,
+}
+
+/// Everything a scene can read or change while handling input or updating.
+pub struct Context<'a> {
+    pub game: &'a mut Game,
+    pub controller: &'a mut Controller,
+    pub sound: &'a mut SoundSystem,
+    pub settings: &'a mut Settings,
+    pub window: &'a winit::window::Window,
+    pub screen_size: glam::Vec2,
+}
+
+impl Context<'_> {
+    pub fn toggle_fullscreen(&mut self) {
+        self.settings.fullscreen = !self.settings.fullscreen;
+        set_fullscreen(self.settings.fullscreen, self.window);
+    }
+}
+
+/// What scenes need to build their meshes.
+pub struct Gfx<'a> {
+    pub device: &'a wgpu::Device,
+    pub atlas: &'a TextureAtlas,
+    pub boxes: &'a BoxRenderer,
+    pub font: &'a Font,
+    pub hud: &'a Hud,
+    pub settings: &'a Settings,
+}
+
+impl Gfx<'_> {
+    /// The arena and HUD for the game in progress.
+    pub fn game(&self, game: &Game, meshes: &mut Vec<Mesh>) {
+        meshes.push(self.boxes.mesh_from_state(self.device, &game.state, self.atlas, game.timestep.alpha()));
+        meshes.push(self.hud.layout(self.device, self.font, &game.state));
+    }
+}
+
+pub trait Scene {
+    fn on_enter(&mut self, _ctx: &mut Context) {}
+    fn on_exit(&mut self, _ctx: &mut Context) {}
+    /// Called when the scene above this one is popped.
+    fn on_resume(&mut self, _ctx: &mut Context) {}
+
+    /// Called after the controller changes.
+    fn input(&mut self, ctx: &mut Context) -> Command;
+
+    /// Called once per frame.
+    fn update(&mut self, _ctx: &mut Context) -> Command {
+        Command::None
+    }
+
+    /// Called when the window loses focus.
+    fn focus_lost(&mut self, _ctx: &mut Context) -> Command {
+        Command::None
+    }
+
+    fn render(&self, game: &Game, gfx: &Gfx, meshes: &mut Vec<Mesh>);
+
+    /// Overlays are drawn on top of the scene below instead of replacing it.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// The music track to play. `None` leaves it to the scene below.
+    fn music<'a>(&'a self, _game: &'a Game) -> Option<&'a str> {
+        None
+    }
+
+    /// Whether the mouse steers the paddle, rather than pointing at things.
+    fn captures_mouse(&self) -> bool {
+        false
+    }
+}
+
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl SceneStack {
+    pub fn new(root: Box<dyn Scene>) -> Self {
+        Self { scenes: vec![root] }
+    }
+
+    pub fn input(&mut self, ctx: &mut Context) -> bool {
+        let command = self.top().input(ctx);
+        self.apply(command, ctx)
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) -> bool {
+        let command = self.top().update(ctx);
+        self.apply(command, ctx)
+    }
+
+    pub fn focus_lost(&mut self, ctx: &mut Context) -> bool {
+        let command = self.top().focus_lost(ctx);
+        self.apply(command, ctx)
+    }
+
+    /// Draws the top scene. An overlay is drawn over the nearest full scene
+    /// beneath it, but not over other overlays in between.
+    pub fn render(&self, game: &Game, gfx: &Gfx) -> Vec<Mesh> {
+        let mut meshes = Vec::new();
+        let top = self.scenes.last().expect("the main menu is never popped");
+        if top.is_overlay() {
+            if let Some(base) = self.scenes.iter().rev().find(|s| !s.is_overlay()) {
+                base.render(game, gfx, &mut meshes);
+            }
+        }
+        top.render(game, gfx, &mut meshes);
+        meshes
+    }
+
+    pub fn music<'a>(&'a self, game: &'a Game) -> Option<&'a str> {
+        self.scenes.iter().rev().find_map(|s| s.music(game))
+    }
+
+    pub fn captures_mouse(&self) -> bool {
+        self.scenes.last().is_some_and(|s| s.captures_mouse())
+    }
+
+    fn top(&mut self) -> &mut dyn Scene {
+        self.scenes.last_mut().expect("the main menu is never popped").as_mut()
+    }
+
+    /// Carries out `command`. Returns `false` if the game should quit.
+    fn apply(&mut self, command: Command, ctx: &mut Context) -> bool {
+        match command {
+            Command::None => (),
+            Command::Push(mut scene) => {
+                scene.on_enter(ctx);
+                self.scenes.push(scene);
+            }
+            Command::Pop => {
+                if self.scenes.len() > 1 {
+                    let mut scene = self.scenes.pop().unwrap();
+                    scene.on_exit(ctx);
+                    self.top().on_resume(ctx);
+                }
+            }
+            Command::Replace(mut scene) => {
+                if self.scenes.len() > 1 {
+                    let mut old = self.scenes.pop().unwrap();
+                    old.on_exit(ctx);
+                }
+                scene.on_enter(ctx);
+                self.scenes.push(scene);
+            }
+            Command::MainMenu => {
+                while self.scenes.len() > 1 {
+                    let mut scene = self.scenes.pop().unwrap();
+                    scene.on_exit(ctx);
+                }
+                self.top().on_enter(ctx);
+            }
+            Command::Quit => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    game::Game,
+    menu::{self, TextMenu},
+    render::Mesh,
+};
+
+use super::{Command, Context, Gfx, Scene, SettingsScene};
+
+/// Freezes the game and shows the pause menu over it.
+pub struct Paused {
+    menu: TextMenu,
+    messages: Vec<menu::Message>,
+}
+
+impl Paused {
+    pub fn new(screen_size: glam::Vec2) -> Self {
+        Self {
+            menu: TextMenu::new(
+                "PAUSED",
+                &[
+                    ("RESUME", menu::Message::Resume),
+                    ("RESTART LEVEL", menu::Message::RestartLevel),
+                    ("SETTINGS", menu::Message::Settings),
+                    ("QUIT TO MENU", menu::Message::MainMenu),
+                ],
+                screen_size,
+            ),
+            messages: Vec::new(),
+        }
+    }
+}
+
+impl Scene for Paused {
+    fn on_exit(&mut self, ctx: &mut Context) {
+        // Don't try to catch up on the time spent paused.
+        ctx.game.timestep.reset();
+    }
+
+    fn input(&mut self, ctx: &mut Context) -> Command {
+        if ctx.controller.back_just_pressed() {
+            return Command::Pop;
+        }
+        self.menu.input(ctx.controller, &mut self.messages);
+
+        let mut command = Command::None;
+        for msg in self.messages.drain(..) {
+            match msg {
+                menu::Message::Resume => command = Command::Pop,
+                menu::Message::RestartLevel => {
+                    command = if ctx.game.restart_level().is_ok() {
+                        Command::Pop
+                    } else {
+                        Command::MainMenu
+                    };
+                }
+                menu::Message::Settings => command = Command::Push(Box::new(SettingsScene::new(ctx.screen_size))),
+                menu::Message::MainMenu => command = Command::MainMenu,
+                menu::Message::FocusChanged => ctx.sound.play_sound("bounce"),
+                _ => (),
+            }
+        }
+        command
+    }
+
+    fn render(&self, _game: &Game, gfx: &Gfx, meshes: &mut Vec<Mesh>) {
+        meshes.push(self.menu.layout(gfx.device, gfx.font, gfx.atlas.size()));
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
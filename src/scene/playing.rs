@@ -0,0 +1,130 @@
+use brick_breaker::system::Message;
+
+use crate::{game::Game, render::Mesh};
+
+use super::{Command, Context, GameOver, Gfx, LevelComplete, Paused, Scene};
+
+/// The game itself.
+pub struct Playing;
+
+impl Scene for Playing {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        ctx.game.steering.resync(ctx.controller);
+    }
+
+    fn on_resume(&mut self, ctx: &mut Context) {
+        ctx.game.steering.resync(ctx.controller);
+    }
+
+    fn input(&mut self, ctx: &mut Context) -> Command {
+        ctx.game.latch_input(ctx.controller);
+        if ctx.controller.back_just_pressed() {
+            Command::Push(Box::new(Paused::new(ctx.screen_size)))
+        } else {
+            Command::None
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> Command {
+        let sound = &mut *ctx.sound;
+        let screen_size = ctx.screen_size;
+        let mut command = Command::None;
+        ctx.game.simulate(ctx.controller, |game, msg| {
+            if matches!(command, Command::None) {
+                sound.on_message(&msg, game.state.arena_size.x);
+            }
+            handle_message(game, msg, screen_size, &mut command)
+        });
+        command
+    }
+
+    fn focus_lost(&mut self, ctx: &mut Context) -> Command {
+        Command::Push(Box::new(Paused::new(ctx.screen_size)))
+    }
+
+    fn music<'a>(&'a self, game: &'a Game) -> Option<&'a str> {
+        let level = &game.campaign.levels[game.current_level];
+        Some(level.music.as_deref().unwrap_or("game"))
+    }
+
+    fn render(&self, game: &Game, gfx: &Gfx, meshes: &mut Vec<Mesh>) {
+        gfx.game(game, meshes);
+    }
+
+    fn captures_mouse(&self) -> bool {
+        true
+    }
+}
+
+/// Reacts to a message from the simulation, setting `command` to the scene it
+/// leads to. Returns `true` once there is one, and ignores everything after
+/// that. A tick sends `GameOver` before `Win`, so losing the last life on the
+/// tick the last brick breaks still ends the game.
+fn handle_message(game: &mut Game, msg: Message, screen_size: glam::Vec2, command: &mut Command) -> bool {
+    if !matches!(command, Command::None) {
+        return true;
+    }
+    match msg {
+        Message::GameOver => *command = Command::Replace(Box::new(GameOver::lost(screen_size))),
+        Message::Win => {
+            *command = if game.advance() {
+                let level = &game.campaign.levels[game.current_level];
+                Command::Push(Box::new(LevelComplete::new(game.current_level, &level.name, screen_size)))
+            } else {
+                Command::Replace(Box::new(GameOver::won(screen_size)))
+            };
+        }
+        Message::LifeLost(lives) => log::info!("Life lost, {} left", lives),
+        Message::LifeGained(lives) => log::info!("Life gained, {} left", lives),
+        Message::PowerUpCollected(kind) => log::info!("Collected {:?}", kind),
+        Message::PowerUpExpired(kind) => log::info!("{:?} wore off", kind),
+        Message::Scored { points, total, combo } => {
+            log::debug!("Scored {} (combo {}), total {}", points, combo, total);
+        }
+        Message::Fire { .. }
+        | Message::Bounce { .. }
+        | Message::Drop
+        | Message::Explode { .. }
+        | Message::LaserFired { .. } => (),
+    }
+    !matches!(command, Command::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brick_breaker::{
+        campaign::Campaign,
+        env::{ARENA_SIZE, BALL_SIZE, BRICK_SIZE, PLAYER_SIZE},
+        input::MouseSettings,
+        state::State,
+        timestep::SIM_STEP,
+    };
+
+    #[test]
+    fn losing_the_last_life_beats_clearing_the_level_on_the_same_tick() {
+        let campaign = Campaign::load("./assets/levels/campaign.ron").unwrap();
+        let state = State::new(ARENA_SIZE, PLAYER_SIZE, BALL_SIZE, BRICK_SIZE);
+        let mut game = Game::new(state, campaign, MouseSettings::default());
+        game.start(0).unwrap();
+
+        // The last ball falls out on the tick the last brick goes.
+        game.state.lives = 1;
+        game.state.bricks.retain(|b| !b.counts_toward_win());
+        let ball = &mut game.state.balls[0];
+        ball.fired = true;
+        ball.body.pos = glam::vec2(0.0, 0.5);
+        ball.vel = glam::vec2(0.0, -120.0);
+        let mut messages = Vec::new();
+        game.movement.update(&mut game.state, SIM_STEP, &mut messages);
+        assert!(messages.iter().any(|m| matches!(m, Message::GameOver)));
+        assert!(messages.iter().any(|m| matches!(m, Message::Win)));
+
+        let mut command = Command::None;
+        for msg in messages {
+            handle_message(&mut game, msg, ARENA_SIZE, &mut command);
+        }
+        assert!(matches!(command, Command::Replace(_)));
+        assert_eq!(game.current_level, 0);
+    }
+}
@@ -0,0 +1,98 @@
+use brick_breaker::input::MouseMode;
+
+use crate::{
+    game::Game,
+    menu::{self, TextMenu},
+    render::Mesh,
+    Settings,
+};
+
+use super::{AudioScene, Command, Context, ControlsScene, Gfx, Scene};
+
+/// Sensitivities the menu cycles through for relative mouse mode.
+const SENSITIVITIES: [f32; 6] = [0.5, 0.75, 1.0, 1.5, 2.0, 3.0];
+
+pub struct SettingsScene {
+    menu: TextMenu,
+    messages: Vec<menu::Message>,
+}
+
+impl SettingsScene {
+    pub fn new(screen_size: glam::Vec2) -> Self {
+        Self {
+            menu: TextMenu::new(
+                "SETTINGS",
+                &[
+                    ("FULLSCREEN", menu::Message::ToggleFullscreen),
+                    ("MOUSE", menu::Message::MouseMode),
+                    ("SENSITIVITY", menu::Message::Sensitivity),
+                    ("CONTROLS", menu::Message::Controls),
+                    ("AUDIO", menu::Message::Audio),
+                    ("BACK", menu::Message::Back),
+                ],
+                screen_size,
+            ),
+            messages: Vec::new(),
+        }
+    }
+
+    fn show(&mut self, settings: &Settings) {
+        let mode = match settings.mouse.mode {
+            MouseMode::Off => "OFF",
+            MouseMode::Absolute => "ABSOLUTE",
+            MouseMode::Relative => "RELATIVE",
+        };
+        self.menu.set_label(menu::Message::MouseMode, format!("MOUSE: {}", mode));
+        self.menu.set_label(
+            menu::Message::Sensitivity,
+            format!("SENSITIVITY x{}", settings.mouse.sensitivity),
+        );
+    }
+}
+
+impl Scene for SettingsScene {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.show(ctx.settings);
+    }
+
+    fn input(&mut self, ctx: &mut Context) -> Command {
+        if ctx.controller.back_just_pressed() {
+            return Command::Pop;
+        }
+        self.menu.input(ctx.controller, &mut self.messages);
+
+        let mut command = Command::None;
+        for msg in self.messages.drain(..) {
+            match msg {
+                menu::Message::ToggleFullscreen => ctx.toggle_fullscreen(),
+                menu::Message::MouseMode => {
+                    ctx.settings.mouse.mode = ctx.settings.mouse.mode.next();
+                }
+                menu::Message::Sensitivity => {
+                    let current = ctx.settings.mouse.sensitivity;
+                    ctx.settings.mouse.sensitivity = SENSITIVITIES
+                        .iter()
+                        .copied()
+                        .find(|&s| s > current)
+                        .unwrap_or(SENSITIVITIES[0]);
+                }
+                menu::Message::Controls => command = Command::Push(Box::new(ControlsScene::new(ctx.screen_size))),
+                menu::Message::Audio => command = Command::Push(Box::new(AudioScene::new(ctx.screen_size))),
+                menu::Message::Back => command = Command::Pop,
+                menu::Message::FocusChanged => ctx.sound.play_sound("bounce"),
+                _ => (),
+            }
+        }
+        ctx.game.steering.settings = ctx.settings.mouse;
+        self.show(ctx.settings);
+        command
+    }
+
+    fn render(&self, _game: &Game, gfx: &Gfx, meshes: &mut Vec<Mesh>) {
+        meshes.push(self.menu.layout(gfx.device, gfx.font, gfx.atlas.size()));
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,86 @@
+/// Points awarded per status tier of a brick.
+const POINTS_PER_TIER: u32 = 10;
+/// Number of consecutive brick hits needed to raise the multiplier by one.
+const HITS_PER_MULTIPLIER: u32 = 3;
+/// The combo multiplier never goes higher than this.
+const MAX_MULTIPLIER: u32 = 8;
+
+/// Base value of hitting a brick with the given `status`. Tougher bricks are
+/// worth more.
+pub fn brick_value(status: u32) -> u32 {
+    status * POINTS_PER_TIER
+}
+
+/// Multiplier for the `combo`th consecutive brick hit since the ball last
+/// touched the paddle. The first few hits score normally.
+pub fn combo_multiplier(combo: u32) -> u32 {
+    (1 + combo.saturating_sub(1) / HITS_PER_MULTIPLIER).min(MAX_MULTIPLIER)
+}
+
+/// Points for hitting a brick with the given `status` as the `combo`th hit in
+/// a row.
+pub fn points_for_hit(status: u32, combo: u32) -> u32 {
+    brick_value(status) * combo_multiplier(combo)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub total: u32,
+    pub combo: u32,
+}
+
+impl Score {
+    /// Records a hit on a brick with the given `status` before it took
+    /// damage. Returns the points awarded.
+    pub fn brick_hit(&mut self, status: u32) -> u32 {
+        self.combo += 1;
+        let points = points_for_hit(status, self.combo);
+        self.total += points;
+        points
+    }
+
+    /// Ends the current combo, e.g. when the ball touches the paddle or drops.
+    pub fn reset_combo(&mut self) {
+        self.combo = 0;
+    }
+
+    pub fn multiplier(&self) -> u32 {
+        combo_multiplier(self.combo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tougher_bricks_are_worth_more() {
+        assert_eq!(brick_value(1), 10);
+        assert_eq!(brick_value(4), 40);
+        assert!(brick_value(3) > brick_value(2));
+    }
+
+    #[test]
+    fn multiplier_grows_with_combo_and_caps() {
+        assert_eq!(combo_multiplier(0), 1);
+        assert_eq!(combo_multiplier(1), 1);
+        assert_eq!(combo_multiplier(3), 1);
+        assert_eq!(combo_multiplier(4), 2);
+        assert_eq!(combo_multiplier(7), 3);
+        assert_eq!(combo_multiplier(1000), MAX_MULTIPLIER);
+    }
+
+    #[test]
+    fn combo_accumulates_until_reset() {
+        let mut score = Score::default();
+        for _ in 0..4 {
+            score.brick_hit(1);
+        }
+        assert_eq!(score.total, 10 * 3 + 20);
+        assert_eq!(score.multiplier(), 2);
+
+        score.reset_combo();
+        assert_eq!(score.brick_hit(2), 20);
+        assert_eq!(score.total, 70);
+    }
+}
@@ -0,0 +1,271 @@
+//! Where sounds end up. The game plays through rodio when there's an output
+//! device and silently when there isn't.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Cursor},
+    path::Path,
+};
+#[cfg(test)]
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use rodio::{source::ChannelVolume, Source};
+
+use super::Clip;
+
+/// A sound to start playing.
+pub struct Sound<'a> {
+    /// Name of the bank the clip came from, as in `sounds.json`.
+    pub name: &'a str,
+    pub clip: &'a Clip,
+    /// From 0 to 1, with every volume setting already applied.
+    pub volume: f32,
+    /// From -1 for the left speaker to 1 for the right.
+    pub pan: f32,
+    /// Playback speed, where 2 is an octave up.
+    pub pitch: f32,
+}
+
+/// Identifies a sound started with [`AudioBackend::play`].
+pub type VoiceId = u32;
+
+/// Identifies a music channel started with [`AudioBackend::start_music`].
+pub type ChannelId = u32;
+
+pub trait AudioBackend {
+    /// Starts playing `sound` straight away, alongside anything already
+    /// playing. Returns `None` if it can't be played.
+    fn play(&mut self, sound: &Sound) -> Option<VoiceId>;
+    /// Cuts a sound off early.
+    fn stop(&mut self, voice: VoiceId);
+    /// Whether a sound is still going. False once it has finished or been
+    /// stopped.
+    fn is_playing(&self, voice: VoiceId) -> bool;
+
+    /// Starts streaming the track at `path` from disk, looping forever.
+    /// Returns `None` if it can't be played.
+    fn start_music(&mut self, path: &Path, volume: f32) -> Option<ChannelId>;
+    fn set_music_volume(&mut self, channel: ChannelId, volume: f32);
+    fn stop_music(&mut self, channel: ChannelId);
+}
+
+/// Plays through the default output device.
+pub struct RodioBackend {
+    /// A sink per sound, so none has to wait for another to finish.
+    voices: HashMap<VoiceId, rodio::Sink>,
+    next_voice: VoiceId,
+    music: HashMap<ChannelId, rodio::Sink>,
+    next_channel: ChannelId,
+    _device: rodio::OutputStream,
+    handle: rodio::OutputStreamHandle,
+}
+
+impl RodioBackend {
+    pub fn new() -> anyhow::Result<Self> {
+        let (device, handle) = rodio::OutputStream::try_default()?;
+        Ok(Self {
+            voices: HashMap::new(),
+            next_voice: 0,
+            music: HashMap::new(),
+            next_channel: 0,
+            _device: device,
+            handle,
+        })
+    }
+}
+
+impl AudioBackend for RodioBackend {
+    fn play(&mut self, sound: &Sound) -> Option<VoiceId> {
+        self.voices.retain(|_, sink| !sink.empty());
+        let start = || -> anyhow::Result<rodio::Sink> {
+            let decoder = rodio::Decoder::new(Cursor::new(sound.clip.clone()))?;
+            // Full volume on both sides when centred, fading out the far
+            // side as the sound moves over.
+            let left = (1.0 - sound.pan).min(1.0);
+            let right = (1.0 + sound.pan).min(1.0);
+            let source = decoder.speed(sound.pitch).amplify(sound.volume);
+            let sink = rodio::Sink::try_new(&self.handle)?;
+            sink.append(ChannelVolume::new(source, vec![left, right]));
+            Ok(sink)
+        };
+        match start() {
+            Ok(sink) => {
+                let voice = self.next_voice;
+                self.next_voice = self.next_voice.wrapping_add(1);
+                self.voices.insert(voice, sink);
+                Some(voice)
+            }
+            Err(e) => {
+                log::error!("Couldn't play \"{}\": {}", sound.name, e);
+                None
+            }
+        }
+    }
+
+    fn stop(&mut self, voice: VoiceId) {
+        if let Some(sink) = self.voices.remove(&voice) {
+            sink.stop();
+        }
+    }
+
+    fn is_playing(&self, voice: VoiceId) -> bool {
+        self.voices.get(&voice).is_some_and(|sink| !sink.empty())
+    }
+
+    fn start_music(&mut self, path: &Path, volume: f32) -> Option<ChannelId> {
+        let stream = || -> anyhow::Result<rodio::Sink> {
+            let decoder = rodio::Decoder::new_looped(BufReader::new(File::open(path)?))?;
+            let sink = rodio::Sink::try_new(&self.handle)?;
+            sink.set_volume(volume);
+            sink.append(decoder);
+            Ok(sink)
+        };
+        match stream() {
+            Ok(sink) => {
+                let channel = self.next_channel;
+                self.next_channel += 1;
+                self.music.insert(channel, sink);
+                Some(channel)
+            }
+            Err(e) => {
+                log::error!("Couldn't play {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn set_music_volume(&mut self, channel: ChannelId, volume: f32) {
+        if let Some(sink) = self.music.get(&channel) {
+            sink.set_volume(volume);
+        }
+    }
+
+    fn stop_music(&mut self, channel: ChannelId) {
+        if let Some(sink) = self.music.remove(&channel) {
+            sink.stop();
+        }
+    }
+}
+
+/// Plays nothing. Used when there's no output device.
+pub struct SilentBackend;
+
+impl AudioBackend for SilentBackend {
+    fn play(&mut self, _sound: &Sound) -> Option<VoiceId> {
+        None
+    }
+
+    fn stop(&mut self, _voice: VoiceId) {}
+
+    fn is_playing(&self, _voice: VoiceId) -> bool {
+        false
+    }
+
+    fn start_music(&mut self, _path: &Path, _volume: f32) -> Option<ChannelId> {
+        None
+    }
+
+    fn set_music_volume(&mut self, _channel: ChannelId, _volume: f32) {}
+
+    fn stop_music(&mut self, _channel: ChannelId) {}
+}
+
+/// A sound a [`RecordingBackend`] was asked to play.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Played {
+    pub voice: VoiceId,
+    pub name: String,
+    pub volume: f32,
+    pub pan: f32,
+    pub pitch: f32,
+    /// Time since the backend was made.
+    pub at: Duration,
+}
+
+/// What a [`RecordingBackend`] has been asked to do.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct Recording {
+    pub played: Vec<Played>,
+    /// Sounds that haven't been stopped. They never finish by themselves,
+    /// so tests take them out to pretend they have.
+    pub playing: BTreeSet<VoiceId>,
+    /// Music channels still playing, with their track and volume.
+    pub music: BTreeMap<ChannelId, (PathBuf, f32)>,
+}
+
+#[cfg(test)]
+pub type SoundLog = Rc<RefCell<Recording>>;
+
+/// Plays nothing, but writes down what it was asked to play so tests can
+/// check which sounds the game triggers.
+#[cfg(test)]
+pub struct RecordingBackend {
+    start: Instant,
+    log: SoundLog,
+}
+
+#[cfg(test)]
+impl RecordingBackend {
+    pub fn new() -> (Self, SoundLog) {
+        let log = SoundLog::default();
+        let backend = Self {
+            start: Instant::now(),
+            log: log.clone(),
+        };
+        (backend, log)
+    }
+}
+
+#[cfg(test)]
+impl AudioBackend for RecordingBackend {
+    fn play(&mut self, sound: &Sound) -> Option<VoiceId> {
+        let at = self.start.elapsed();
+        log::debug!("Played \"{}\" at {:?}", sound.name, at);
+        let mut log = self.log.borrow_mut();
+        let voice = log.played.len() as VoiceId;
+        log.playing.insert(voice);
+        log.played.push(Played {
+            voice,
+            name: sound.name.to_string(),
+            volume: sound.volume,
+            pan: sound.pan,
+            pitch: sound.pitch,
+            at,
+        });
+        Some(voice)
+    }
+
+    fn stop(&mut self, voice: VoiceId) {
+        self.log.borrow_mut().playing.remove(&voice);
+    }
+
+    fn is_playing(&self, voice: VoiceId) -> bool {
+        self.log.borrow().playing.contains(&voice)
+    }
+
+    fn start_music(&mut self, path: &Path, volume: f32) -> Option<ChannelId> {
+        let mut log = self.log.borrow_mut();
+        let channel = log.music.keys().last().map_or(0, |c| c + 1);
+        log.music.insert(channel, (path.to_owned(), volume));
+        Some(channel)
+    }
+
+    fn set_music_volume(&mut self, channel: ChannelId, volume: f32) {
+        if let Some(music) = self.log.borrow_mut().music.get_mut(&channel) {
+            music.1 = volume;
+        }
+    }
+
+    fn stop_music(&mut self, channel: ChannelId) {
+        self.log.borrow_mut().music.remove(&channel);
+    }
+}
@@ -0,0 +1,535 @@
+mod backend;
+mod music;
+
+use brick_breaker::system::Message;
+use rand::prelude::*;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+pub use backend::{AudioBackend, RodioBackend, SilentBackend, Sound, VoiceId};
+use music::Music;
+#[cfg(test)]
+pub use backend::{RecordingBackend, SoundLog};
+
+/// An encoded sound file, loaded into memory once and shared by every play.
+pub type Clip = Arc<[u8]>;
+
+/// What a sound is, for deciding which volume slider applies to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+pub enum Category {
+    #[default]
+    Sfx,
+    Music,
+}
+
+/// Volumes from 0 to 1. Saved in the settings.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub sfx: f32,
+    pub music: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            sfx: 1.0,
+            music: 0.7,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// How loud to play something in `category`.
+    pub fn volume(&self, category: Category) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        let category = match category {
+            Category::Sfx => self.sfx,
+            Category::Music => self.music,
+        };
+        (self.master * category).clamp(0.0, 1.0)
+    }
+}
+
+/// The most combo steps a sound's pitch goes up by.
+pub const MAX_COMBO_STEPS: u32 = 12;
+
+/// Where and how to play a sound.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Emitter {
+    /// From -1 for the left speaker to 1 for the right.
+    pub pan: f32,
+    /// How far into a combo chain the sound is, for raising its pitch.
+    pub combo: u32,
+}
+
+impl Emitter {
+    /// Pans to `x` across an arena `width` wide.
+    pub fn at(x: f32, width: f32) -> Self {
+        Self {
+            pan: (x / width * 2.0 - 1.0).clamp(-1.0, 1.0),
+            combo: 0,
+        }
+    }
+
+    pub fn combo(self, combo: u32) -> Self {
+        Self { combo, ..self }
+    }
+}
+
+/// How many sound effects can play at once.
+pub const VOICES: usize = 16;
+
+/// Seconds over which a sound's `rate_limit` applies.
+pub const RATE_WINDOW: f32 = 0.1;
+
+/// A sound effect the backend is playing.
+struct Voice {
+    id: VoiceId,
+    name: String,
+    priority: u8,
+}
+
+pub struct SoundSystem {
+    rng: ThreadRng,
+    backend: Box<dyn AudioBackend>,
+    banks: HashMap<String, SoundBank>,
+    /// Oldest first.
+    voices: Vec<Voice>,
+    /// Seconds of `update`s so far.
+    time: f32,
+    music: Music,
+    pub settings: AudioSettings,
+}
+
+impl SoundSystem {
+    /// Loads the sounds described by `json` and plays them on the default
+    /// output device, or silently if there isn't one.
+    pub fn with_json(json: &str) -> anyhow::Result<Self> {
+        let backend: Box<dyn AudioBackend> = match RodioBackend::new() {
+            Ok(backend) => Box::new(backend),
+            Err(e) => {
+                log::warn!("No audio output, playing without sound: {}", e);
+                Box::new(SilentBackend)
+            }
+        };
+        Self::with_backend(json, backend)
+    }
+
+    pub fn with_backend(json: &str, backend: Box<dyn AudioBackend>) -> anyhow::Result<Self> {
+        let atlas: HashMap<String, SoundDef> = serde_json::from_str(json)?;
+        let rng = rand::thread_rng();
+
+        let mut banks = HashMap::new();
+        for (k, v) in atlas.into_iter() {
+            let sources = v
+                .files
+                .into_iter()
+                .map(|f| Ok(Clip::from(std::fs::read(f)?)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            banks.insert(
+                k,
+                SoundBank {
+                    sources,
+                    category: v.category,
+                    gain: v.gain,
+                    volume_jitter: v.volume_jitter,
+                    pitch_jitter: v.pitch_jitter,
+                    combo_step: v.combo_step,
+                    priority: v.priority,
+                    max_voices: v.max_voices,
+                    rate_limit: v.rate_limit,
+                    recent: VecDeque::new(),
+                },
+            );
+        }
+
+        Ok(Self {
+            rng,
+            backend,
+            banks,
+            voices: Vec::new(),
+            time: 0.0,
+            music: Music::default(),
+            settings: AudioSettings::default(),
+        })
+    }
+
+    /// Loads the music tracks listed in `json`, the contents of
+    /// `assets/music.json`.
+    pub fn load_music(&mut self, json: &str) -> anyhow::Result<()> {
+        self.music = Music::with_json(json)?;
+        Ok(())
+    }
+
+    /// Crossfades to the music track called `name`, or to silence.
+    pub fn play_music(&mut self, name: Option<&str>) {
+        self.music.play(name, self.backend.as_mut());
+    }
+
+    /// Call once a frame with the time since the last one.
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+        let volume = self.settings.volume(Category::Music);
+        self.music.update(dt, volume, self.backend.as_mut());
+    }
+
+    /// Plays `name` centred, at its usual pitch.
+    pub fn play_sound(&mut self, name: &str) {
+        self.play_sound_at(name, Emitter::default());
+    }
+
+    /// Starts `name` on a free voice. If there isn't one, the oldest sound
+    /// of the lowest priority that's no higher than this one's is cut off
+    /// to make room, and failing that this one is dropped.
+    pub fn play_sound_at(&mut self, name: &str, emitter: Emitter) {
+        let Some(bank) = self.banks.get_mut(name) else {
+            return;
+        };
+        let volume = bank.gain * (1.0 + jitter(&mut self.rng, bank.volume_jitter));
+        let volume = (volume * self.settings.volume(bank.category)).clamp(0.0, 1.0);
+        if volume <= 0.0 {
+            return;
+        }
+
+        let time = self.time;
+        bank.recent.retain(|&t| time - t < RATE_WINDOW);
+        if bank.rate_limit.is_some_and(|limit| bank.recent.len() >= limit) {
+            log::trace!("Dropped \"{}\", played too often", name);
+            return;
+        }
+
+        let backend = &mut self.backend;
+        self.voices.retain(|v| backend.is_playing(v.id));
+        let playing = self.voices.iter().filter(|v| v.name == name).count();
+        let victim = if bank.max_voices.is_some_and(|max| playing >= max) {
+            self.voices.iter().position(|v| v.name == name)
+        } else if self.voices.len() >= VOICES {
+            let candidates = self.voices.iter().enumerate().filter(|(_, v)| v.priority <= bank.priority);
+            match candidates.min_by_key(|(_, v)| v.priority) {
+                Some((i, _)) => Some(i),
+                None => {
+                    log::trace!("Dropped \"{}\", no voice free", name);
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        if let Some(i) = victim {
+            backend.stop(self.voices.remove(i).id);
+        }
+
+        let semitones = emitter.combo.min(MAX_COMBO_STEPS) as f32 * bank.combo_step
+            + jitter(&mut self.rng, bank.pitch_jitter);
+        let clip = bank.random(&mut self.rng);
+        let voice = backend.play(&Sound {
+            name,
+            clip,
+            volume,
+            pan: emitter.pan,
+            pitch: 2f32.powf(semitones / 12.0),
+        });
+        if let Some(id) = voice {
+            self.voices.push(Voice {
+                id,
+                name: name.to_string(),
+                priority: bank.priority,
+            });
+        }
+        bank.recent.push_back(time);
+    }
+
+    /// Plays whatever sound goes with something that happened in the game,
+    /// panned across an arena `width` wide.
+    pub fn on_message(&mut self, msg: &Message, width: f32) {
+        let (name, emitter) = match *msg {
+            Message::Fire { x } | Message::Explode { x } | Message::LaserFired { x } => {
+                ("fire", Emitter::at(x, width))
+            }
+            Message::PowerUpCollected(_) => ("fire", Emitter::default()),
+            Message::Bounce { x, combo } => ("bounce", Emitter::at(x, width).combo(combo)),
+            Message::Drop => ("fail", Emitter::default()),
+            Message::Win => ("win", Emitter::default()),
+            Message::LifeGained(_) => ("life", Emitter::default()),
+            Message::LifeLost(_) | Message::GameOver | Message::PowerUpExpired(_) | Message::Scored { .. } => {
+                return
+            }
+        };
+        self.play_sound_at(name, emitter);
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct SoundDef {
+    files: Vec<String>,
+    #[serde(default)]
+    category: Category,
+    /// Scales this sound relative to the others in its category.
+    #[serde(default = "default_gain")]
+    gain: f32,
+    /// Each play is up to this fraction louder or quieter.
+    #[serde(default)]
+    volume_jitter: f32,
+    /// Each play is up to this many semitones higher or lower.
+    #[serde(default)]
+    pitch_jitter: f32,
+    /// Semitones to go up by for each step of a combo chain.
+    #[serde(default)]
+    combo_step: f32,
+    /// Higher priority sounds can cut off lower ones when every voice is
+    /// busy.
+    #[serde(default)]
+    priority: u8,
+    /// Most copies of this sound that can play at once. Another one cuts
+    /// off the oldest.
+    #[serde(default)]
+    max_voices: Option<usize>,
+    /// Most times this sound can start within [`RATE_WINDOW`]. Any more are
+    /// dropped.
+    #[serde(default)]
+    rate_limit: Option<usize>,
+}
+
+fn default_gain() -> f32 {
+    1.0
+}
+
+/// A random offset of up to `range` either way.
+fn jitter(rng: &mut ThreadRng, range: f32) -> f32 {
+    if range > 0.0 {
+        rng.gen_range(-range..=range)
+    } else {
+        0.0
+    }
+}
+
+pub struct SoundBank {
+    sources: Vec<Clip>,
+    category: Category,
+    gain: f32,
+    volume_jitter: f32,
+    pitch_jitter: f32,
+    combo_step: f32,
+    priority: u8,
+    max_voices: Option<usize>,
+    rate_limit: Option<usize>,
+    /// When this sound started within the last [`RATE_WINDOW`].
+    recent: VecDeque<f32>,
+}
+
+impl SoundBank {
+    pub fn random(&self, rng: &mut ThreadRng) -> &Clip {
+        let i = rng.gen_range(0..self.sources.len());
+        &self.sources[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brick_breaker::{
+        env::{ARENA_SIZE, BALL_SIZE, BRICK_SIZE, PLAYER_SIZE},
+        level::Level,
+        state::State,
+        system::{MovementSystem, PADDLE_SPEED},
+        timestep::SIM_STEP,
+    };
+
+    fn recorded() -> (SoundSystem, SoundLog) {
+        recorded_with(&std::fs::read_to_string("./assets/sounds.json").unwrap())
+    }
+
+    fn recorded_with(json: &str) -> (SoundSystem, SoundLog) {
+        let (backend, log) = RecordingBackend::new();
+        (SoundSystem::with_backend(json, Box::new(backend)).unwrap(), log)
+    }
+
+    #[test]
+    fn brick_hit_plays_bounce() {
+        let (mut sound, log) = recorded();
+
+        let level = Level::from_ron(
+            r#"Level(
+                name: "Test",
+                drop_rate: 0.0,
+                legend: { '1': (hits: 2) },
+                grid: ["1111111111"],
+            )"#,
+        )
+        .unwrap();
+        let mut state = State::new(ARENA_SIZE, PLAYER_SIZE, BALL_SIZE, BRICK_SIZE);
+        state.new_game();
+        state.load_level(&level).unwrap();
+        let ball = &mut state.balls[0];
+        ball.fired = true;
+        ball.vel = glam::vec2(0.0, 40.0);
+
+        let movement = MovementSystem::new(PADDLE_SPEED);
+        let mut messages = Vec::new();
+        while !messages.iter().any(|m| matches!(m, Message::Scored { .. })) {
+            movement.update(&mut state, SIM_STEP, &mut messages);
+        }
+        for msg in &messages {
+            sound.on_message(msg, ARENA_SIZE.x);
+        }
+
+        let played: Vec<_> = log.borrow().played.iter().map(|p| p.name.clone()).collect();
+        assert_eq!(played, ["bounce"]);
+    }
+
+    #[test]
+    fn volume_scales_by_gain_category_and_master() {
+        let (mut sound, log) = recorded_with(
+            r#"{
+                "bounce": { "gain": 0.8, "files": ["./assets/bounce_1.wav"] },
+                "win": { "files": ["./assets/win.wav"] }
+            }"#,
+        );
+        sound.settings = AudioSettings {
+            master: 0.5,
+            sfx: 0.5,
+            music: 1.0,
+            muted: false,
+        };
+        sound.play_sound("win");
+        sound.play_sound("bounce");
+        sound.settings.muted = true;
+        sound.play_sound("win");
+
+        let volumes: Vec<_> = log.borrow().played.iter().map(|p| p.volume).collect();
+        // Muted sounds aren't played at all.
+        assert_eq!(volumes, [0.25, 0.2]);
+    }
+
+    #[test]
+    fn pans_with_position_and_rises_with_combo() {
+        let (mut sound, log) = recorded_with(
+            r#"{
+                "bounce": {
+                    "files": ["./assets/bounce_1.wav"],
+                    "volume_jitter": 0.1,
+                    "pitch_jitter": 0.4,
+                    "combo_step": 1.0
+                }
+            }"#,
+        );
+        for (x, combo) in [(0.0, 0), (10.0, 1), (20.0, 2), (20.0, 40)] {
+            sound.on_message(&Message::Bounce { x, combo }, 20.0);
+        }
+
+        let played = log.borrow().played.clone();
+        let pans: Vec<_> = played.iter().map(|p| p.pan).collect();
+        assert_eq!(pans, [-1.0, 0.0, 1.0, 1.0]);
+        // A semitone per step outweighs the jitter, and long chains stop
+        // rising eventually.
+        assert!(played.windows(2).all(|w| w[0].pitch < w[1].pitch));
+        let most = 2f32.powf((MAX_COMBO_STEPS as f32 + 0.4) / 12.0);
+        assert!(played[3].pitch <= most);
+        assert!(played.iter().all(|p| (0.9..=1.0).contains(&p.volume)));
+    }
+
+    const VOICE_TEST_SOUNDS: &str = r#"{
+        "bounce": { "files": ["./assets/bounce_1.wav"], "max_voices": 2, "rate_limit": 3 },
+        "fire": { "files": ["./assets/fire_1.wav"], "priority": 1 },
+        "win": { "files": ["./assets/win.wav"], "priority": 2 }
+    }"#;
+
+    fn playing(log: &SoundLog) -> Vec<String> {
+        let log = log.borrow();
+        log.playing.iter().map(|&v| log.played[v as usize].name.clone()).collect()
+    }
+
+    #[test]
+    fn repeats_cut_off_their_oldest_copy_and_drop_when_too_frequent() {
+        let (mut sound, log) = recorded_with(VOICE_TEST_SOUNDS);
+        for _ in 0..4 {
+            sound.play_sound("bounce");
+        }
+        // The fourth comes too soon after the first three.
+        assert_eq!(log.borrow().played.len(), 3);
+        assert_eq!(log.borrow().playing.iter().copied().collect::<Vec<_>>(), [1, 2]);
+
+        sound.update(RATE_WINDOW);
+        sound.play_sound("bounce");
+        assert_eq!(log.borrow().played.len(), 4);
+    }
+
+    #[test]
+    fn full_pool_steals_from_lower_priorities() {
+        let (mut sound, log) = recorded_with(VOICE_TEST_SOUNDS);
+        for _ in 0..VOICES - 1 {
+            sound.play_sound("fire");
+        }
+        sound.play_sound("win");
+        sound.play_sound("bounce");
+        assert_eq!(playing(&log).iter().filter(|n| *n == "fire").count(), VOICES - 1);
+        assert_eq!(log.borrow().played.len(), VOICES);
+
+        sound.play_sound("win");
+        let names = playing(&log);
+        assert_eq!(names.len(), VOICES);
+        assert_eq!(names.iter().filter(|n| *n == "win").count(), 2);
+
+        // Finished sounds free their voices.
+        log.borrow_mut().playing.clear();
+        sound.play_sound("bounce");
+        assert_eq!(playing(&log), ["bounce"]);
+    }
+
+    #[test]
+    fn music_crossfades_between_tracks() {
+        let (mut sound, log) = recorded();
+        sound
+            .load_music(
+                r#"{
+                    "crossfade": 1.0,
+                    "tracks": {
+                        "menu": { "file": "./assets/music/menu.wav" },
+                        "game": { "file": "./assets/music/game.wav", "gain": 0.5 }
+                    }
+                }"#,
+            )
+            .unwrap();
+        sound.settings.music = 1.0;
+        let volumes = || -> Vec<_> { log.borrow().music.values().map(|m| m.1).collect() };
+
+        sound.play_music(Some("menu"));
+        sound.update(1.0);
+        assert_eq!(volumes(), [1.0]);
+
+        sound.play_music(Some("game"));
+        // Asking again mid-fade doesn't restart it.
+        sound.play_music(Some("game"));
+        sound.update(0.5);
+        assert_eq!(volumes(), [0.5, 0.25]);
+
+        sound.update(0.5);
+        assert_eq!(volumes(), [0.5]);
+        assert!(log.borrow().music.values().all(|m| m.0.ends_with("game.wav")));
+    }
+
+    #[test]
+    fn unknown_track_fades_to_silence_once() {
+        let (mut sound, log) = recorded();
+        sound.load_music(r#"{ "tracks": { "menu": { "file": "./assets/music/menu.wav" } } }"#).unwrap();
+
+        sound.play_music(Some("menu"));
+        sound.play_music(Some("nope"));
+        sound.update(1.0);
+        assert!(log.borrow().music.is_empty());
+
+        // Asking every frame doesn't go looking for it again.
+        sound.play_music(Some("nope"));
+        sound.play_music(Some("menu"));
+        assert_eq!(log.borrow().music.len(), 1);
+    }
+}
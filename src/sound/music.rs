@@ -0,0 +1,113 @@
+//! Background music: one looping track at a time, streamed from disk, with
+//! a crossfade whenever it changes. Tracks are listed in `assets/music.json`.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use super::{backend::ChannelId, AudioBackend};
+
+#[derive(Debug, serde::Deserialize)]
+struct Manifest {
+    /// Seconds to fade from one track to the next.
+    #[serde(default = "default_crossfade")]
+    crossfade: f32,
+    tracks: HashMap<String, TrackDef>,
+}
+
+fn default_crossfade() -> f32 {
+    1.0
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TrackDef {
+    file: PathBuf,
+    #[serde(default = "super::default_gain")]
+    gain: f32,
+}
+
+/// A track that's playing, or fading out.
+struct Channel {
+    channel: Option<ChannelId>,
+    gain: f32,
+    /// How far faded in, from 0 to 1.
+    level: f32,
+}
+
+#[derive(Default)]
+pub struct Music {
+    tracks: HashMap<String, TrackDef>,
+    crossfade: f32,
+    /// The last track asked for, even if there's no such track.
+    requested: Option<String>,
+    current: Option<Channel>,
+    fading: Vec<Channel>,
+}
+
+impl Music {
+    pub fn with_json(json: &str) -> anyhow::Result<Self> {
+        let manifest: Manifest = serde_json::from_str(json)?;
+        let tracks = manifest
+            .tracks
+            .into_iter()
+            .filter(|(name, def)| {
+                let exists = def.file.exists();
+                if !exists {
+                    log::warn!("Music track \"{}\" is missing: {}", name, def.file.display());
+                }
+                exists
+            })
+            .collect();
+        Ok(Self {
+            tracks,
+            crossfade: manifest.crossfade.max(0.0),
+            ..Default::default()
+        })
+    }
+
+    /// Fades over to `track`, or to silence if it's `None` or doesn't exist.
+    /// Does nothing if that's what was asked for last time.
+    pub fn play(&mut self, track: Option<&str>, backend: &mut dyn AudioBackend) {
+        if self.requested.as_deref() == track {
+            return;
+        }
+        self.requested = track.map(str::to_string);
+        self.fading.extend(self.current.take());
+
+        let Some(name) = track else {
+            return;
+        };
+        match self.tracks.get(name) {
+            Some(def) => {
+                self.current = Some(Channel {
+                    channel: backend.start_music(&def.file, 0.0),
+                    gain: def.gain,
+                    level: 0.0,
+                });
+            }
+            None => log::warn!("No music track called \"{}\"", name),
+        }
+    }
+
+    /// Moves the crossfade along by `dt` seconds and applies `volume`.
+    pub fn update(&mut self, dt: f32, volume: f32, backend: &mut dyn AudioBackend) {
+        let step = if self.crossfade > 0.0 { dt / self.crossfade } else { 1.0 };
+        if let Some(current) = &mut self.current {
+            current.level = (current.level + step).min(1.0);
+        }
+        for channel in &mut self.fading {
+            channel.level -= step;
+        }
+        self.fading.retain(|c| {
+            let done = c.level <= 0.0;
+            if let (true, Some(channel)) = (done, c.channel) {
+                backend.stop_music(channel);
+            }
+            !done
+        });
+
+        for c in self.fading.iter().chain(&self.current) {
+            if let Some(channel) = c.channel {
+                backend.set_music_volume(channel, c.level * c.gain * volume);
+            }
+        }
+    }
+}
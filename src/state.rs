@@ -1,10 +1,35 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    level::{Level, LevelError},
+    powerup::{Effect, PowerUp, PowerUpKind, SLOW_FACTOR},
+    score::Score,
+};
+
+/// How many lives the player has at the start of a game.
+pub const STARTING_LIVES: u32 = 3;
+
 pub struct State {
     pub game_just_started: bool,
+    pub lives: u32,
+    pub score: Score,
     pub bricks: Vec<Brick>,
     pub player: Player,
-    pub ball: Ball,
+    pub balls: Vec<Ball>,
     pub arena_size: glam::Vec2,
     pub brick_size: glam::Vec2,
+    pub ball_size: glam::Vec2,
+    pub ball_speed: f32,
+    /// Paddle width before any power-ups.
+    pub paddle_width: f32,
+    /// Chance of a destroyed brick dropping a power-up.
+    pub drop_rate: f32,
+    pub power_ups: Vec<PowerUp>,
+    pub effects: Vec<Effect>,
+    pub bolts: Vec<Body>,
+    pub laser_cooldown: f32,
+    /// Drives everything random in the simulation so runs can be replayed.
+    pub rng: StdRng,
 }
 
 impl State {
@@ -19,8 +44,20 @@ impl State {
             arena_size.x * 0.5 - ball_size.x * 0.5,
             player_pos.y + player_size.y + ball_size.y * 0.5,
         );
+        let ball = Ball {
+            body: Body {
+                pos: ball_pos,
+                size: ball_size,
+            },
+            vel: glam::Vec2::ZERO,
+            fired: false,
+            prev_pos: ball_pos,
+            attach_offset: ball_pos.x - player_pos.x,
+        };
         Self {
             game_just_started: false,
+            lives: STARTING_LIVES,
+            score: Score::default(),
             bricks: Vec::new(),
             player: Player {
                 body: Body {
@@ -28,39 +65,111 @@ impl State {
                     size: player_size,
                 },
                 vel: glam::Vec2::ZERO,
+                prev_pos: player_pos,
             },
-            ball: Ball {
-                body: Body {
-                    pos: ball_pos,
-                    size: ball_size,
-                },
-                vel: glam::Vec2::ZERO,
-                fired: false,
-            },
+            balls: vec![ball],
             arena_size,
             brick_size,
+            ball_size,
+            ball_speed: 5.0,
+            paddle_width: player_size.x,
+            drop_rate: 0.0,
+            power_ups: Vec::new(),
+            effects: Vec::new(),
+            bolts: Vec::new(),
+            laser_cooldown: 0.0,
+            rng: StdRng::seed_from_u64(0),
         }
     }
 
-    pub fn setup(&mut self, num_x: u32, num_y: u32) {
+    /// Restarts the random number generator so the same seed and inputs
+    /// always play out the same way.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Starts a fresh game with full lives and no score.
+    pub fn new_game(&mut self) {
+        self.lives = STARTING_LIVES;
+        self.score = Score::default();
+    }
+
+    /// Replaces the bricks with the ones from `level`, centred at the top of
+    /// the arena, and applies the level's paddle and ball settings.
+    pub fn load_level(&mut self, level: &Level) -> Result<(), LevelError> {
+        level.validate(self.arena_size, self.brick_size, self.ball_size)?;
+
         self.game_just_started = true;
-        self.bricks.clear();
-        self.player.body.pos = glam::vec2(self.arena_size.x * 0.5 - self.brick_size.x * 0.5, 0.0);
-        let padding = self.arena_size.x - self.brick_size.x * num_x as f32;
+        self.ball_speed = level.ball_speed;
+        self.paddle_width = level.paddle_width;
+        self.drop_rate = level.drop_rate;
+
+        let padding = self.arena_size.x - self.brick_size.x * level.cols() as f32;
         let start_x = padding * 0.5;
-        for y in 0..num_y {
-            for x in 0..num_x {
-                self.bricks.push(Brick {
-                    body: Body {
-                        pos: glam::vec2(
-                            start_x + x as f32 * self.brick_size.x,
-                            self.arena_size.y - y as f32 * self.brick_size.y - self.brick_size.y,
-                        ),
-                        size: self.brick_size,
-                    },
-                    status: 4 - y % 4,
-                });
-            }
+        self.bricks = level
+            .bricks()?
+            .into_iter()
+            .map(|b| {
+                let body = Body {
+                    pos: glam::vec2(
+                        start_x + b.col as f32 * self.brick_size.x,
+                        self.arena_size.y - b.row as f32 * self.brick_size.y - self.brick_size.y,
+                    ),
+                    size: self.brick_size,
+                };
+                Brick::new(body, b.def.hits, b.def.kind)
+            })
+            .collect();
+
+        self.reset_round();
+        Ok(())
+    }
+
+    /// Puts the paddle back in the middle with a single ball ready to fire.
+    /// Used at the start of a game and whenever a life is lost.
+    pub fn reset_round(&mut self) {
+        self.effects.clear();
+        self.power_ups.clear();
+        self.bolts.clear();
+        self.laser_cooldown = 0.0;
+        self.player.body.size.x = self.paddle_width;
+        self.player.body.pos = glam::vec2((self.arena_size.x - self.player.body.size.x) * 0.5, 0.0);
+        self.player.prev_pos = self.player.body.pos;
+        self.balls.clear();
+        self.balls.push(self.new_ball());
+    }
+
+    /// Whether every brick that needs breaking is gone.
+    pub fn is_cleared(&self) -> bool {
+        !self.bricks.iter().any(Brick::counts_toward_win)
+    }
+
+    pub fn has_effect(&self, kind: PowerUpKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    /// Ball speed after power-ups are taken into account.
+    pub fn current_ball_speed(&self) -> f32 {
+        if self.has_effect(PowerUpKind::SlowBall) {
+            self.ball_speed * SLOW_FACTOR
+        } else {
+            self.ball_speed
+        }
+    }
+
+    /// A ball resting on top of the paddle, waiting to be fired.
+    pub fn new_ball(&self) -> Ball {
+        let attach_offset = (self.player.body.size.x - self.ball_size.x) * 0.5;
+        let pos = self.player.body.pos + glam::vec2(attach_offset, 1.0 + self.player.body.size.y);
+        Ball {
+            body: Body {
+                pos,
+                size: self.ball_size,
+            },
+            vel: glam::Vec2::ZERO,
+            fired: false,
+            prev_pos: pos,
+            attach_offset,
         }
     }
 }
@@ -71,16 +180,78 @@ pub struct Body {
     pub size: glam::Vec2,
 }
 
+impl Body {
+    pub fn center(&self) -> glam::Vec2 {
+        self.pos + self.size * 0.5
+    }
+}
+
+/// Seconds a damaged regenerating brick has to go untouched before it heals.
+pub const REGEN_DELAY: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+pub enum BrickKind {
+    /// Breaks after `status` hits.
+    #[default]
+    Normal,
+    /// Can't be broken and doesn't need to be cleared to win.
+    Indestructible,
+    /// Damages the bricks around it when it breaks.
+    Explosive,
+    /// Heals one hit at a time if left alone for `REGEN_DELAY` seconds.
+    Regenerating,
+    /// Only takes damage from powered-up attacks.
+    Reinforced,
+}
+
+impl BrickKind {
+    pub fn counts_toward_win(self) -> bool {
+        self != BrickKind::Indestructible
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Brick {
     pub body: Body,
+    /// Hits left before the brick breaks.
     pub status: u32,
+    pub kind: BrickKind,
+    /// What `status` started at, used as the cap when regenerating.
+    pub max_status: u32,
+    /// Time until a regenerating brick heals its next hit.
+    pub regen_timer: f32,
+}
+
+impl Brick {
+    pub fn new(body: Body, status: u32, kind: BrickKind) -> Self {
+        Self {
+            body,
+            status,
+            kind,
+            max_status: status,
+            regen_timer: REGEN_DELAY,
+        }
+    }
+
+    /// Whether the brick has to be destroyed to clear the level.
+    pub fn counts_toward_win(&self) -> bool {
+        self.kind.counts_toward_win()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Player {
     pub body: Body,
     pub vel: glam::Vec2,
+    /// Position at the start of the last simulation step, for interpolation.
+    pub prev_pos: glam::Vec2,
+}
+
+impl Player {
+    /// Where to draw the paddle `alpha` of the way through the next step.
+    pub fn lerp_pos(&self, alpha: f32) -> glam::Vec2 {
+        self.prev_pos.lerp(self.body.pos, alpha)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -88,4 +259,15 @@ pub struct Ball {
     pub body: Body,
     pub vel: glam::Vec2,
     pub fired: bool,
+    /// Position at the start of the last simulation step, for interpolation.
+    pub prev_pos: glam::Vec2,
+    /// Where the ball sits along the paddle while it isn't fired.
+    pub attach_offset: f32,
+}
+
+impl Ball {
+    /// Where to draw the ball `alpha` of the way through the next step.
+    pub fn lerp_pos(&self, alpha: f32) -> glam::Vec2 {
+        self.prev_pos.lerp(self.body.pos, alpha)
+    }
 }
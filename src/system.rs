@@ -1,16 +1,45 @@
+use rand::Rng;
+
 use crate::{
-    input::Controller,
-    state::{self, State},
+    input::{Controls, TickInput},
+    powerup::{
+        Effect, PowerUp, PowerUpKind, BOLT_SIZE, BOLT_SPEED, CAPSULE_SIZE, CAPSULE_SPEED, LASER_COOLDOWN,
+        MULTI_BALL_SPREAD, WIDE_FACTOR,
+    },
+    state::{self, BrickKind, State, REGEN_DELAY},
 };
 
 #[derive(Debug, Clone, Copy)]
 pub enum Message {
     Win,
-    Bounce,
+    /// A ball bounced off something. `x` is where, across the arena, and
+    /// `combo` the brick hits since the ball last touched the paddle.
+    Bounce { x: f32, combo: u32 },
     Drop,
-    Fire,
+    /// A ball left the paddle at `x`.
+    Fire { x: f32 },
+    /// The player lost a life. Holds the number of lives left.
+    LifeLost(u32),
+    /// The player earned a life. Holds the new number of lives.
+    LifeGained(u32),
+    /// The last life is gone.
+    GameOver,
+    /// A brick hit scored `points`, bringing the score to `total`. `combo`
+    /// counts the brick hits since the ball last touched the paddle.
+    Scored { points: u32, total: u32, combo: u32 },
+    /// An explosive brick went off at `x`.
+    Explode { x: f32 },
+    /// The paddle caught a power-up capsule.
+    PowerUpCollected(PowerUpKind),
+    /// A timed power-up wore off.
+    PowerUpExpired(PowerUpKind),
+    /// The paddle shot a pair of laser bolts from `x`.
+    LaserFired { x: f32 },
 }
 
+/// How fast the paddle moves, in arena units per second.
+pub const PADDLE_SPEED: f32 = 10.0;
+
 pub struct MovementSystem {
     speed: f32,
     dir: f32,
@@ -26,12 +55,25 @@ impl MovementSystem {
         }
     }
 
-    pub fn input(&mut self, controller: &Controller) {
-        self.dir = controller.dir();
-        self.fire = controller.fire_just_pressed();
+    pub fn input<C: Controls + ?Sized>(&mut self, controls: &C) {
+        self.dir = controls.dir();
+        self.fire = controls.fire_just_pressed();
+    }
+
+    /// The input the next `update` will act on, for recording replays.
+    pub fn last_input(&self) -> TickInput {
+        TickInput {
+            dir: self.dir,
+            fire: self.fire,
+        }
     }
 
     pub fn update(&self, state: &mut State, dt: f32, messages: &mut Vec<Message>) {
+        state.player.prev_pos = state.player.body.pos;
+        for ball in &mut state.balls {
+            ball.prev_pos = ball.body.pos;
+        }
+
         state.player.vel.x = self.dir * self.speed;
         state.player.body.pos += state.player.vel * dt;
 
@@ -41,81 +83,557 @@ impl MovementSystem {
             state.player.body.pos.x = state.arena_size.x - state.player.body.size.x
         }
 
-        if self.fire && !state.ball.fired && !state.game_just_started {
-            state.ball.vel = glam::vec2(self.dir, 1.0).normalize() * self.speed * 0.5;
-            state.ball.fired = true;
-            messages.push(Message::Fire);
-        } else if !state.ball.fired {
-            state.ball.body.pos = state.player.body.pos
-                + glam::vec2(
-                    (state.player.body.size.x - state.ball.body.size.x) * 0.5,
-                    1.0 + state.player.body.size.y,
-                );
+        for brick in &mut state.bricks {
+            if brick.kind == BrickKind::Regenerating && brick.status < brick.max_status {
+                brick.regen_timer -= dt;
+                if brick.regen_timer <= 0.0 {
+                    brick.status += 1;
+                    brick.regen_timer = REGEN_DELAY;
+                }
+            }
         }
 
-        state.ball.body.pos += state.ball.vel * dt;
+        self.tick_effects(state, dt, messages);
+
+        let mut fired = None;
+        let speed = state.current_ball_speed();
+        for ball in state.balls.iter_mut().filter(|b| !b.fired) {
+            if self.fire && !state.game_just_started {
+                // The mouse can move the paddle faster than the keys, but
+                // shouldn't launch the ball any flatter.
+                ball.vel = glam::vec2(self.dir.clamp(-1.0, 1.0), 1.0).normalize() * speed;
+                ball.fired = true;
+                fired = Some(ball.body.center().x);
+            } else {
+                // The paddle may have shrunk since the ball was caught.
+                ball.attach_offset = ball
+                    .attach_offset
+                    .clamp(0.0, (state.player.body.size.x - ball.body.size.x).max(0.0));
+                ball.body.pos =
+                    state.player.body.pos + glam::vec2(ball.attach_offset, 1.0 + state.player.body.size.y);
+            }
+        }
+        if let Some(x) = fired {
+            messages.push(Message::Fire { x });
+        }
+
+        state.laser_cooldown = (state.laser_cooldown - dt).max(0.0);
+        if self.fire && fired.is_none() && state.has_effect(PowerUpKind::Laser) && state.laser_cooldown <= 0.0 {
+            let paddle = state.player.body;
+            let y = paddle.pos.y + paddle.size.y;
+            for x in [paddle.pos.x + 1.0, paddle.pos.x + paddle.size.x - 1.0 - BOLT_SIZE.x] {
+                state.bolts.push(state::Body {
+                    pos: glam::vec2(x, y),
+                    size: BOLT_SIZE,
+                });
+            }
+            state.laser_cooldown = LASER_COOLDOWN;
+            messages.push(Message::LaserFired { x: paddle.center().x });
+        }
+        move_bolts(state, dt, messages);
+        self.move_power_ups(state, dt, messages);
 
         // collision
-        if state.ball.fired {
-            let mut bounced = false;
-            if collide(&state.ball.body, &state.player.body) {
-                let paddle_rel_x = state.ball.body.pos.x - state.player.body.pos.x;
-                state.ball.vel.x = paddle_rel_x / (state.player.body.size.x - state.ball.body.size.x) * 2.0 - 1.0;
-                state.ball.vel.y = 2.0;
-                state.ball.vel = state.ball.vel.normalize() * 0.5 * self.speed;
-                bounced = true;
-            }
-    
-            let mut bricks_to_remove = Vec::new();
-            for (i, brick) in state.bricks.iter_mut().enumerate() {
-                if collide(&state.ball.body, &brick.body) {
-                    state.ball.body.pos.y = brick.body.pos.y - brick.body.size.y - state.ball.body.size.y;
-                    state.ball.vel.y *= -1.0;
-    
-                    if brick.status > 0 {
-                        brick.status -= 1;
-                    }
-    
-                    if brick.status <= 0 {
-                        bricks_to_remove.push(i);
-                    }
-                    bounced = true;
+        if state.balls.iter().any(|b| b.fired) {
+            let mut bounced = None;
+            for i in 0..state.balls.len() {
+                if state.balls[i].fired {
+                    bounced = self.move_ball(state, i, dt, messages).or(bounced);
                 }
             }
-    
-            for i in bricks_to_remove {
-                state.bricks.remove(i);
-            }
-    
-            if state.ball.body.pos.x < 0.0 {
-                state.ball.body.pos.x = 0.0;
-                state.ball.vel.x *= -1.0;
-                bounced = true;
-            } else if state.ball.body.pos.x + state.ball.body.size.x > state.arena_size.x {
-                state.ball.body.pos.x = state.arena_size.x - state.ball.body.size.x;
-                state.ball.vel.x *= -1.0;
-                bounced = true;
-            }
-            if state.ball.body.pos.y < 0.0 {
+
+            // Balls that fall out of the arena are gone for good. Only losing
+            // the last one costs the player anything.
+            state.balls.retain(|b| b.body.pos.y >= 0.0);
+            if state.balls.is_empty() {
                 messages.push(Message::Drop);
-                bounced = false;
-                state.ball.fired = false;
-            } else if state.ball.body.pos.y + state.ball.body.size.y > state.arena_size.y {
-                state.ball.body.pos.y = state.arena_size.y - state.ball.body.size.y;
-                state.ball.vel.y *= -1.0;
-                bounced = true;
-            }
-    
-            if state.bricks.len() == 0 {
+                bounced = None;
+                state.score.reset_combo();
+                state.lives = state.lives.saturating_sub(1);
+                messages.push(Message::LifeLost(state.lives));
+                if state.lives == 0 {
+                    messages.push(Message::GameOver);
+                }
+                state.reset_round();
+            }
+
+            if state.is_cleared() {
                 messages.push(Message::Win);
             }
-            if bounced {
-                messages.push(Message::Bounce);
+            if let Some(x) = bounced {
+                let combo = state.score.combo;
+                messages.push(Message::Bounce { x, combo });
             }
         }
 
         state.game_just_started = false;
     }
+
+    /// Awards the player an extra life.
+    pub fn gain_life(&self, state: &mut State, messages: &mut Vec<Message>) {
+        state.lives += 1;
+        messages.push(Message::LifeGained(state.lives));
+    }
+
+    /// Counts down timed power-ups and undoes the ones that run out.
+    fn tick_effects(&self, state: &mut State, dt: f32, messages: &mut Vec<Message>) {
+        for effect in &mut state.effects {
+            effect.remaining -= dt;
+        }
+        let expired: Vec<_> = state
+            .effects
+            .iter()
+            .filter(|e| e.remaining <= 0.0)
+            .map(|e| e.kind)
+            .collect();
+        state.effects.retain(|e| e.remaining > 0.0);
+        for kind in expired {
+            match kind {
+                PowerUpKind::WidePaddle => set_paddle_width(state, state.paddle_width),
+                PowerUpKind::SlowBall => rescale_balls(state),
+                _ => (),
+            }
+            messages.push(Message::PowerUpExpired(kind));
+        }
+    }
+
+    /// Drops capsules towards the paddle, applying any that get caught.
+    fn move_power_ups(&self, state: &mut State, dt: f32, messages: &mut Vec<Message>) {
+        let mut collected = Vec::new();
+        let player = state.player.body;
+        state.power_ups.retain_mut(|p| {
+            p.body.pos.y -= CAPSULE_SPEED * dt;
+            if collide(&p.body, &player) {
+                collected.push(p.kind);
+                return false;
+            }
+            p.body.pos.y + p.body.size.y >= 0.0
+        });
+        for kind in collected {
+            self.apply_power_up(state, kind, messages);
+        }
+    }
+
+    fn apply_power_up(&self, state: &mut State, kind: PowerUpKind, messages: &mut Vec<Message>) {
+        messages.push(Message::PowerUpCollected(kind));
+        match kind {
+            PowerUpKind::WidePaddle if !state.has_effect(kind) => {
+                set_paddle_width(state, state.paddle_width * WIDE_FACTOR);
+            }
+            PowerUpKind::MultiBall => {
+                // A ball waiting on the paddle sends the new ones off
+                // upwards, so the power-up isn't wasted.
+                let launch = glam::vec2(0.0, state.current_ball_speed());
+                let spawned: Vec<_> = state
+                    .balls
+                    .iter()
+                    .flat_map(|b| {
+                        let vel = if b.fired { b.vel } else { launch };
+                        [-MULTI_BALL_SPREAD, MULTI_BALL_SPREAD].map(|angle| state::Ball {
+                            vel: glam::Vec2::from_angle(angle).rotate(vel),
+                            fired: true,
+                            ..*b
+                        })
+                    })
+                    .collect();
+                state.balls.extend(spawned);
+            }
+            PowerUpKind::ExtraLife => self.gain_life(state, messages),
+            _ => (),
+        }
+
+        // Catching a timed power-up again only refreshes its timer.
+        if let Some(duration) = kind.duration() {
+            match state.effects.iter_mut().find(|e| e.kind == kind) {
+                Some(effect) => effect.remaining = duration,
+                None => {
+                    state.effects.push(Effect {
+                        kind,
+                        remaining: duration,
+                    });
+                    if kind == PowerUpKind::SlowBall {
+                        rescale_balls(state);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Moves the ball at `index` through the arena for `dt` seconds, resolving
+    /// every contact along the way. Returns where it ended up across the arena
+    /// if it bounced off anything.
+    fn move_ball(
+        &self,
+        state: &mut State,
+        index: usize,
+        dt: f32,
+        messages: &mut Vec<Message>,
+    ) -> Option<f32> {
+        let mut ball = state.balls[index];
+        let mut bounced = false;
+        let mut remaining = dt;
+        let mut contacts = Vec::new();
+        for _ in 0..MAX_CONTACTS_PER_STEP {
+            let delta = ball.vel * remaining;
+            let toi = match earliest_contacts(state, &ball.body, delta, &mut contacts) {
+                Some(toi) => toi,
+                None => break,
+            };
+
+            ball.body.pos += delta * toi;
+            remaining *= 1.0 - toi;
+            bounced = true;
+
+            // Everything touched at the same instant is resolved as one
+            // surface, so a ball landing on the seam between two bricks
+            // bounces once instead of flipping twice.
+            let mut normal = glam::Vec2::ZERO;
+            let mut corner = glam::Vec2::ZERO;
+            let mut paddle = None;
+            let mut hit_bricks = Vec::new();
+            for (contact, hit) in contacts.drain(..) {
+                ball.body.pos += hit.normal * hit.depth;
+                match contact {
+                    Contact::Paddle => paddle = Some(hit),
+                    Contact::Brick(i) => hit_bricks.push(i),
+                    Contact::Wall => (),
+                }
+                if hit.face == Face::Corner {
+                    corner += hit.normal;
+                } else {
+                    normal += hit.normal;
+                }
+            }
+            if normal == glam::Vec2::ZERO {
+                normal = corner;
+            }
+
+            match paddle {
+                Some(hit) if hit.normal.y > 0.0 && state.has_effect(PowerUpKind::StickyPaddle) => {
+                    // Hold on to the ball until the player fires it again.
+                    ball.attach_offset = ball.body.pos.x - state.player.body.pos.x;
+                    ball.vel = glam::Vec2::ZERO;
+                    ball.fired = false;
+                    state.score.reset_combo();
+                }
+                Some(hit) if hit.normal.y > 0.0 => {
+                    let paddle_rel_x = ball.body.pos.x - state.player.body.pos.x;
+                    ball.vel.x = paddle_rel_x / (state.player.body.size.x - ball.body.size.x) * 2.0 - 1.0;
+                    ball.vel.y = 2.0;
+                    ball.vel = ball.vel.normalize() * state.current_ball_speed();
+                    state.score.reset_combo();
+                }
+                _ => ball.vel = reflect(ball.vel, normal),
+            }
+
+            damage_bricks(state, hit_bricks, false, messages);
+            if !ball.fired {
+                break;
+            }
+        }
+        ball.body.pos += ball.vel * remaining;
+
+        // The paddle can squeeze the ball against a wall, where no push-out
+        // satisfies both. The walls win.
+        let max = state.arena_size - ball.body.size;
+        ball.body.pos.x = ball.body.pos.x.clamp(0.0, max.x);
+        ball.body.pos.y = ball.body.pos.y.min(max.y);
+
+        state.balls[index] = ball;
+        bounced.then(|| ball.body.center().x)
+    }
+}
+
+/// Resizes the paddle around its center, keeping it inside the arena.
+fn set_paddle_width(state: &mut State, width: f32) {
+    let body = &mut state.player.body;
+    body.pos.x += (body.size.x - width) * 0.5;
+    body.size.x = width;
+    body.pos.x = body.pos.x.clamp(0.0, state.arena_size.x - width);
+}
+
+/// Brings every moving ball up or down to the current ball speed.
+fn rescale_balls(state: &mut State) {
+    let speed = state.current_ball_speed();
+    for ball in state.balls.iter_mut().filter(|b| b.fired) {
+        ball.vel = ball.vel.normalize_or_zero() * speed;
+    }
+}
+
+/// Moves laser bolts upwards. Each bolt stops at the first brick in its way
+/// and can damage reinforced bricks.
+fn move_bolts(state: &mut State, dt: f32, messages: &mut Vec<Message>) {
+    let delta = glam::vec2(0.0, BOLT_SPEED * dt);
+    let mut hits = Vec::new();
+    let bricks = &state.bricks;
+    state.bolts.retain_mut(|bolt| {
+        let hit = bricks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, brick)| sweep(bolt, delta, &brick.body).map(|hit| (i, hit.toi)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match hit {
+            Some((i, _)) => {
+                hits.push(i);
+                false
+            }
+            None => {
+                bolt.pos += delta;
+                bolt.pos.y < state.arena_size.y
+            }
+        }
+    });
+    hits.sort_unstable();
+    hits.dedup();
+    damage_bricks(state, hits, true, messages);
+}
+
+/// Deals one hit to each brick in `hits`, setting off any explosive bricks
+/// that break, then clears out everything destroyed. `powered` attacks can
+/// damage reinforced bricks.
+fn damage_bricks(state: &mut State, mut hits: Vec<usize>, powered: bool, messages: &mut Vec<Message>) {
+    while let Some(i) = hits.pop() {
+        let brick = &mut state.bricks[i];
+        let immune = match brick.kind {
+            BrickKind::Indestructible => true,
+            BrickKind::Reinforced => !powered,
+            _ => false,
+        };
+        // Explosions can reach a brick more than once.
+        if immune || brick.status == 0 {
+            continue;
+        }
+
+        let points = state.score.brick_hit(brick.status);
+        messages.push(Message::Scored {
+            points,
+            total: state.score.total,
+            combo: state.score.combo,
+        });
+        brick.status -= 1;
+        brick.regen_timer = REGEN_DELAY;
+
+        if brick.status == 0 && state.rng.gen::<f32>() < state.drop_rate {
+            let kind = PowerUpKind::ALL[state.rng.gen_range(0..PowerUpKind::ALL.len())];
+            let center = brick.body.pos + brick.body.size * 0.5;
+            state.power_ups.push(PowerUp {
+                body: state::Body {
+                    pos: center - CAPSULE_SIZE * 0.5,
+                    size: CAPSULE_SIZE,
+                },
+                kind,
+            });
+        }
+
+        let brick = &state.bricks[i];
+        if brick.status == 0 && brick.kind == BrickKind::Explosive {
+            let center = brick.body.center();
+            messages.push(Message::Explode { x: center.x });
+            let reach = brick.body.size * 1.5;
+            hits.extend(state.bricks.iter().enumerate().filter_map(|(j, other)| {
+                let offset = (other.body.pos + other.body.size * 0.5 - center).abs();
+                (j != i && offset.x < reach.x && offset.y < reach.y).then_some(j)
+            }));
+        }
+    }
+
+    state.bricks.retain(|b| b.status > 0);
+}
+
+/// Upper bound on how many bounces the ball can resolve in a single step.
+const MAX_CONTACTS_PER_STEP: usize = 8;
+
+/// Contacts whose times of impact are this close are treated as simultaneous.
+const TOI_EPSILON: f32 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Contact {
+    Paddle,
+    Brick(usize),
+    Wall,
+}
+
+/// Which side of the static body the ball struck.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Face {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    Corner,
+}
+
+impl Face {
+    fn from_normal(normal: glam::Vec2) -> Self {
+        match (normal.x != 0.0, normal.y != 0.0) {
+            (true, true) => Face::Corner,
+            (true, false) if normal.x < 0.0 => Face::Left,
+            (true, false) => Face::Right,
+            _ if normal.y < 0.0 => Face::Bottom,
+            _ => Face::Top,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hit {
+    /// Fraction of the motion segment travelled before contact, in `[0, 1]`.
+    toi: f32,
+    /// Outward normal of the face that was hit. Each component is -1, 0 or 1,
+    /// with both set for corner hits.
+    normal: glam::Vec2,
+    face: Face,
+    /// How far the ball has to be pushed along `normal` to stop overlapping.
+    depth: f32,
+}
+
+impl Hit {
+    fn new(toi: f32, normal: glam::Vec2, depth: f32) -> Self {
+        Self {
+            toi,
+            normal,
+            face: Face::from_normal(normal),
+            depth,
+        }
+    }
+}
+
+/// Collects everything `ball` touches first when moving by `delta` this
+/// step into `out` and returns the time of impact.
+fn earliest_contacts(
+    state: &State,
+    ball: &state::Body,
+    delta: glam::Vec2,
+    out: &mut Vec<(Contact, Hit)>,
+) -> Option<f32> {
+    let mut earliest = f32::INFINITY;
+    let mut consider = |contact, hit: Option<Hit>| {
+        if let Some(hit) = hit {
+            if hit.toi < earliest - TOI_EPSILON {
+                earliest = hit.toi;
+                out.clear();
+            }
+            if hit.toi <= earliest + TOI_EPSILON {
+                out.push((contact, hit));
+            }
+        }
+    };
+
+    consider(Contact::Paddle, sweep(ball, delta, &state.player.body));
+    for (i, brick) in state.bricks.iter().enumerate() {
+        consider(Contact::Brick(i), sweep(ball, delta, &brick.body));
+    }
+    for wall in walls(state.arena_size) {
+        consider(Contact::Wall, sweep(ball, delta, &wall));
+    }
+
+    if out.is_empty() {
+        None
+    } else {
+        Some(earliest)
+    }
+}
+
+/// The left, right and top edges of the arena as solid bodies. They are as
+/// thick as the arena itself so nothing can skip over them. The bottom is
+/// left open so the ball can drop.
+fn walls(arena_size: glam::Vec2) -> [state::Body; 3] {
+    let height = arena_size.y * 3.0;
+    [
+        state::Body {
+            pos: glam::vec2(-arena_size.x, -arena_size.y),
+            size: glam::vec2(arena_size.x, height),
+        },
+        state::Body {
+            pos: glam::vec2(arena_size.x, -arena_size.y),
+            size: glam::vec2(arena_size.x, height),
+        },
+        state::Body {
+            pos: glam::vec2(-arena_size.x, arena_size.y),
+            size: glam::vec2(arena_size.x * 3.0, arena_size.y),
+        },
+    ]
+}
+
+/// Swept AABB test of `a` moving by `delta` against the static body `b`.
+/// Returns the time of impact as a fraction of `delta` along with the face of
+/// `b` that was hit.
+fn sweep(a: &state::Body, delta: glam::Vec2, b: &state::Body) -> Option<Hit> {
+    if collide(a, b) {
+        // Already overlapping, so push back out along the shallowest axis,
+        // but only if we're moving further in.
+        let (normal, depth) = penetration(a, b);
+        return if delta.dot(normal) < 0.0 {
+            Some(Hit::new(0.0, normal, depth))
+        } else {
+            None
+        };
+    }
+
+    let (entry_x, exit_x) = axis_times(a.pos.x, a.size.x, b.pos.x, b.size.x, delta.x)?;
+    let (entry_y, exit_y) = axis_times(a.pos.y, a.size.y, b.pos.y, b.size.y, delta.y)?;
+
+    let entry = entry_x.max(entry_y);
+    let exit = exit_x.min(exit_y);
+    if entry > exit || !(0.0..=1.0).contains(&entry) {
+        return None;
+    }
+
+    let normal_x = glam::vec2(-delta.x.signum(), 0.0);
+    let normal_y = glam::vec2(0.0, -delta.y.signum());
+    let normal = if (entry_x - entry_y).abs() <= TOI_EPSILON {
+        normal_x + normal_y
+    } else if entry_x > entry_y {
+        normal_x
+    } else {
+        normal_y
+    };
+    Some(Hit::new(entry, normal, 0.0))
+}
+
+/// Entry and exit times along a single axis. Returns `None` if the spans never
+/// overlap on this axis.
+fn axis_times(a_min: f32, a_size: f32, b_min: f32, b_size: f32, d: f32) -> Option<(f32, f32)> {
+    let a_max = a_min + a_size;
+    let b_max = b_min + b_size;
+    if d == 0.0 {
+        return if a_max > b_min && a_min < b_max {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        } else {
+            None
+        };
+    }
+    let (near, far) = if d > 0.0 {
+        (b_min - a_max, b_max - a_min)
+    } else {
+        (b_max - a_min, b_min - a_max)
+    };
+    Some((near / d, far / d))
+}
+
+/// Separating normal and depth for two overlapping bodies, picked from the
+/// axis with the least overlap.
+fn penetration(a: &state::Body, b: &state::Body) -> (glam::Vec2, f32) {
+    let a_center = a.pos + a.size * 0.5;
+    let b_center = b.pos + b.size * 0.5;
+    let offset = a_center - b_center;
+    let overlap = (a.size + b.size) * 0.5 - offset.abs();
+    if (overlap.x - overlap.y).abs() <= TOI_EPSILON {
+        (glam::vec2(offset.x.signum(), offset.y.signum()), overlap.x)
+    } else if overlap.x < overlap.y {
+        (glam::vec2(offset.x.signum(), 0.0), overlap.x)
+    } else {
+        (glam::vec2(0.0, offset.y.signum()), overlap.y)
+    }
+}
+
+/// Flips each velocity component that is heading into `normal`. Components
+/// already moving away are left alone so overlapping contacts can't cancel.
+fn reflect(v: glam::Vec2, normal: glam::Vec2) -> glam::Vec2 {
+    glam::vec2(
+        if v.x * normal.x < 0.0 { -v.x } else { v.x },
+        if v.y * normal.y < 0.0 { -v.y } else { v.y },
+    )
 }
 
 fn collide(a: &state::Body, b: &state::Body) -> bool {
@@ -124,3 +642,416 @@ fn collide(a: &state::Body, b: &state::Body) -> bool {
         && a.pos.y < b.pos.y + b.size.y
         && a.pos.y + a.size.y > b.pos.y
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_state() -> State {
+        let mut state = State::new(
+            glam::vec2(80.0, 80.0),
+            glam::vec2(16.0, 3.0),
+            glam::vec2(4.0, 4.0),
+            glam::vec2(8.0, 4.0),
+        );
+        state.game_just_started = false;
+        state
+    }
+
+    fn brick_at(pos: glam::Vec2, status: u32) -> state::Brick {
+        kind_at(pos, status, BrickKind::Normal)
+    }
+
+    fn kind_at(pos: glam::Vec2, status: u32, kind: BrickKind) -> state::Brick {
+        let body = state::Body {
+            pos,
+            size: glam::vec2(8.0, 4.0),
+        };
+        state::Brick::new(body, status, kind)
+    }
+
+    #[test]
+    fn sweep_finds_thin_brick_skipped_by_discrete_step() {
+        let ball = state::Body {
+            pos: glam::vec2(10.0, 0.0),
+            size: glam::vec2(4.0, 4.0),
+        };
+        let brick = brick_at(glam::vec2(8.0, 40.0), 1).body;
+        let delta = glam::vec2(0.0, 100.0);
+
+        let moved = state::Body {
+            pos: ball.pos + delta,
+            ..ball
+        };
+        assert!(!collide(&moved, &brick));
+
+        let hit = sweep(&ball, delta, &brick).unwrap();
+        assert!((hit.toi - 0.36).abs() < 1e-5);
+        assert_eq!(hit.normal, glam::vec2(0.0, -1.0));
+    }
+
+    #[test]
+    fn fast_ball_breaks_brick_instead_of_tunnelling() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(8.0, 40.0), 1));
+        state.bricks.push(brick_at(glam::vec2(60.0, 70.0), 1));
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = glam::vec2(10.0, 10.0);
+        state.balls[0].vel = glam::vec2(0.0, 3000.0);
+
+        let mut messages = Vec::new();
+        MovementSystem::new(10.0).update(&mut state, 1.0 / 60.0, &mut messages);
+
+        assert_eq!(state.bricks.len(), 1);
+        assert!(state.balls[0].vel.y < 0.0);
+        assert!(state.balls[0].body.pos.y + state.balls[0].body.size.y <= 40.0);
+        assert!(matches!(
+            messages.as_slice(),
+            [Message::Scored { points: 10, total: 10, combo: 1 }, Message::Bounce { combo: 1, .. }]
+        ));
+    }
+
+    #[test]
+    fn fast_ball_bounces_off_paddle() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(0.0, 76.0), 1));
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = state.player.body.pos + glam::vec2(6.0, 20.0);
+        state.balls[0].vel = glam::vec2(0.0, -3000.0);
+
+        let mut messages = Vec::new();
+        MovementSystem::new(10.0).update(&mut state, 1.0 / 60.0, &mut messages);
+
+        assert!(state.balls[0].vel.y > 0.0);
+        assert!(state.balls[0].body.pos.y >= state.player.body.pos.y + state.player.body.size.y);
+        assert!(!messages.iter().any(|m| matches!(m, Message::Drop)));
+    }
+
+    #[test]
+    fn resolves_several_contacts_in_one_step() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(0.0, 0.0), 1));
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = glam::vec2(70.0, 70.0);
+        state.balls[0].vel = glam::vec2(600.0, 600.0);
+
+        let mut messages = Vec::new();
+        MovementSystem::new(10.0).update(&mut state, 1.0 / 60.0, &mut messages);
+
+        // Bounced off both the right wall and the ceiling.
+        assert!(state.balls[0].vel.x < 0.0);
+        assert!(state.balls[0].vel.y < 0.0);
+        let ball = &state.balls[0].body;
+        assert!(ball.pos.x >= 0.0 && ball.pos.x + ball.size.x <= state.arena_size.x);
+        assert!(ball.pos.y >= 0.0 && ball.pos.y + ball.size.y <= state.arena_size.y);
+    }
+
+    #[test]
+    fn side_hit_reflects_horizontal_velocity() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(40.0, 40.0), 2));
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = glam::vec2(30.0, 40.0);
+        state.balls[0].vel = glam::vec2(600.0, 0.0);
+
+        let mut messages = Vec::new();
+        MovementSystem::new(10.0).update(&mut state, 1.0 / 60.0, &mut messages);
+
+        assert_eq!(state.balls[0].vel, glam::vec2(-600.0, 0.0));
+        assert!(state.balls[0].body.pos.x + state.balls[0].body.size.x <= 40.0);
+        assert_eq!(state.bricks[0].status, 1);
+    }
+
+    #[test]
+    fn hit_from_above_sends_ball_back_up() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(40.0, 40.0), 2));
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = glam::vec2(42.0, 50.0);
+        state.balls[0].vel = glam::vec2(0.0, -600.0);
+
+        let mut messages = Vec::new();
+        MovementSystem::new(10.0).update(&mut state, 1.0 / 60.0, &mut messages);
+
+        assert!(state.balls[0].vel.y > 0.0);
+        assert!(state.balls[0].body.pos.y >= 44.0);
+    }
+
+    #[test]
+    fn seam_between_bricks_is_one_flat_bounce() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(32.0, 40.0), 2));
+        state.bricks.push(brick_at(glam::vec2(40.0, 40.0), 2));
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = glam::vec2(38.0, 30.0);
+        state.balls[0].vel = glam::vec2(0.0, 600.0);
+
+        let mut messages = Vec::new();
+        MovementSystem::new(10.0).update(&mut state, 1.0 / 60.0, &mut messages);
+
+        assert_eq!(state.balls[0].vel, glam::vec2(0.0, -600.0));
+        assert!(state.bricks.iter().all(|b| b.status == 1));
+    }
+
+    #[test]
+    fn corner_hit_reflects_both_components() {
+        let brick = brick_at(glam::vec2(40.0, 40.0), 1).body;
+        let ball = state::Body {
+            pos: glam::vec2(32.0, 32.0),
+            size: glam::vec2(4.0, 4.0),
+        };
+        let hit = sweep(&ball, glam::vec2(8.0, 8.0), &brick).unwrap();
+        assert_eq!(hit.face, Face::Corner);
+        assert_eq!(reflect(glam::vec2(1.0, 1.0), hit.normal), glam::vec2(-1.0, -1.0));
+    }
+
+    #[test]
+    fn overlapping_ball_is_pushed_out() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(40.0, 40.0), 2));
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = glam::vec2(42.0, 37.0);
+        state.balls[0].vel = glam::vec2(0.0, 60.0);
+
+        let mut messages = Vec::new();
+        MovementSystem::new(10.0).update(&mut state, 1.0 / 60.0, &mut messages);
+
+        assert!(state.balls[0].vel.y < 0.0);
+        assert!(state.balls[0].body.pos.y + state.balls[0].body.size.y <= 40.0);
+    }
+
+    #[test]
+    fn life_is_only_lost_with_the_last_ball() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(0.0, 76.0), 1));
+        let mut ball = state.balls[0];
+        ball.fired = true;
+        ball.body.pos = glam::vec2(70.0, 2.0);
+        ball.vel = glam::vec2(0.0, -600.0);
+        state.balls[0] = ball;
+        ball.body.pos = glam::vec2(40.0, 40.0);
+        ball.vel = glam::vec2(0.0, 60.0);
+        state.balls.push(ball);
+
+        let system = MovementSystem::new(10.0);
+        let mut messages = Vec::new();
+        system.update(&mut state, 1.0 / 60.0, &mut messages);
+        assert_eq!(state.balls.len(), 1);
+        assert!(!messages.iter().any(|m| matches!(m, Message::Drop)));
+
+        state.balls[0].body.pos = glam::vec2(70.0, 2.0);
+        state.balls[0].vel = glam::vec2(0.0, -600.0);
+        messages.clear();
+        system.update(&mut state, 1.0 / 60.0, &mut messages);
+        assert!(messages.iter().any(|m| matches!(m, Message::Drop)));
+        assert!(messages.iter().any(|m| matches!(m, Message::LifeLost(2))));
+        assert_eq!(state.balls.len(), 1);
+        assert!(!state.balls[0].fired);
+    }
+
+    #[test]
+    fn losing_the_last_life_ends_the_game() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(0.0, 76.0), 1));
+        state.lives = 1;
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = glam::vec2(70.0, 2.0);
+        state.balls[0].vel = glam::vec2(0.0, -600.0);
+
+        let mut messages = Vec::new();
+        MovementSystem::new(10.0).update(&mut state, 1.0 / 60.0, &mut messages);
+
+        assert_eq!(state.lives, 0);
+        assert!(matches!(
+            messages.as_slice(),
+            [Message::Drop, Message::LifeLost(0), Message::GameOver]
+        ));
+    }
+
+    #[test]
+    fn explosive_brick_damages_neighbours() {
+        let mut state = test_state();
+        state.bricks.push(kind_at(glam::vec2(40.0, 40.0), 1, BrickKind::Explosive));
+        state.bricks.push(brick_at(glam::vec2(48.0, 40.0), 1));
+        state.bricks.push(brick_at(glam::vec2(32.0, 44.0), 2));
+        state.bricks.push(kind_at(glam::vec2(40.0, 44.0), 1, BrickKind::Indestructible));
+        state.bricks.push(brick_at(glam::vec2(8.0, 40.0), 1));
+
+        let mut messages = Vec::new();
+        damage_bricks(&mut state, vec![0], false, &mut messages);
+
+        assert!(messages.iter().any(|m| matches!(m, Message::Explode { .. })));
+        let left: Vec<_> = state.bricks.iter().map(|b| (b.body.pos, b.status)).collect();
+        assert_eq!(
+            left,
+            vec![
+                (glam::vec2(32.0, 44.0), 1),
+                (glam::vec2(40.0, 44.0), 1),
+                (glam::vec2(8.0, 40.0), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn indestructible_bricks_dont_block_the_win() {
+        let mut state = test_state();
+        state.bricks.push(kind_at(glam::vec2(8.0, 40.0), 1, BrickKind::Indestructible));
+        state.bricks.push(kind_at(glam::vec2(40.0, 40.0), 1, BrickKind::Reinforced));
+
+        let mut messages = Vec::new();
+        damage_bricks(&mut state, vec![0, 1], false, &mut messages);
+        assert_eq!(state.bricks.len(), 2);
+        assert!(!state.is_cleared());
+
+        damage_bricks(&mut state, vec![1], true, &mut messages);
+        assert_eq!(state.bricks.len(), 1);
+        assert!(state.is_cleared());
+    }
+
+    #[test]
+    fn regenerating_brick_heals_when_left_alone() {
+        let mut state = test_state();
+        state.bricks.push(kind_at(glam::vec2(8.0, 60.0), 2, BrickKind::Regenerating));
+        state.bricks.push(brick_at(glam::vec2(40.0, 60.0), 1));
+        let mut messages = Vec::new();
+        damage_bricks(&mut state, vec![0], false, &mut messages);
+        assert_eq!(state.bricks[0].status, 1);
+
+        let system = MovementSystem::new(10.0);
+        let steps = (REGEN_DELAY * 60.0) as usize + 1;
+        for _ in 0..steps {
+            system.update(&mut state, 1.0 / 60.0, &mut messages);
+        }
+        assert_eq!(state.bricks[0].status, 2);
+    }
+
+    fn drops(seed: u64) -> Vec<PowerUpKind> {
+        let mut state = test_state();
+        state.reseed(seed);
+        state.drop_rate = 0.5;
+        for i in 0..10 {
+            state.bricks.push(brick_at(glam::vec2(i as f32 * 8.0, 60.0), 1));
+        }
+        damage_bricks(&mut state, (0..10).collect(), false, &mut Vec::new());
+        state.power_ups.iter().map(|p| p.kind).collect()
+    }
+
+    #[test]
+    fn drops_are_reproducible_from_the_seed() {
+        assert_eq!(drops(7), drops(7));
+        assert!(!drops(7).is_empty());
+    }
+
+    #[test]
+    fn caught_capsule_widens_paddle_until_it_wears_off() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(0.0, 70.0), 1));
+        let center = state.player.body.pos.x + state.player.body.size.x * 0.5;
+        state.power_ups.push(PowerUp {
+            body: state::Body {
+                pos: state.player.body.pos + glam::vec2(4.0, 2.0),
+                size: CAPSULE_SIZE,
+            },
+            kind: PowerUpKind::WidePaddle,
+        });
+
+        let system = MovementSystem::new(10.0);
+        let mut messages = Vec::new();
+        system.update(&mut state, 1.0 / 60.0, &mut messages);
+        assert!(state.power_ups.is_empty());
+        assert_eq!(state.player.body.size.x, 16.0 * WIDE_FACTOR);
+        assert_eq!(state.player.body.pos.x + state.player.body.size.x * 0.5, center);
+
+        let steps = (PowerUpKind::WidePaddle.duration().unwrap() * 60.0) as usize + 1;
+        for _ in 0..steps {
+            system.update(&mut state, 1.0 / 60.0, &mut messages);
+        }
+        assert_eq!(state.player.body.size.x, 16.0);
+        assert!(messages.iter().any(|m| matches!(m, Message::PowerUpExpired(PowerUpKind::WidePaddle))));
+    }
+
+    #[test]
+    fn multi_ball_launches_from_a_ball_on_the_paddle() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(0.0, 70.0), 1));
+        let system = MovementSystem::new(10.0);
+        let mut messages = Vec::new();
+        system.apply_power_up(&mut state, PowerUpKind::MultiBall, &mut messages);
+
+        assert_eq!(state.balls.len(), 3);
+        assert!(!state.balls[0].fired);
+        for ball in &state.balls[1..] {
+            assert!(ball.fired);
+            assert!(ball.vel.y > 0.0);
+            assert!((ball.vel.length() - state.ball_speed).abs() < 1e-4);
+        }
+        assert!(state.balls[1].vel.x * state.balls[2].vel.x < 0.0);
+    }
+
+    #[test]
+    fn sticky_paddle_catches_the_ball() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(0.0, 70.0), 1));
+        state.effects.push(Effect {
+            kind: PowerUpKind::StickyPaddle,
+            remaining: 10.0,
+        });
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = state.player.body.pos + glam::vec2(2.0, 10.0);
+        state.balls[0].vel = glam::vec2(0.0, -600.0);
+
+        let mut messages = Vec::new();
+        MovementSystem::new(10.0).update(&mut state, 1.0 / 60.0, &mut messages);
+
+        assert!(!state.balls[0].fired);
+        assert_eq!(state.balls[0].vel, glam::Vec2::ZERO);
+        assert_eq!(state.balls[0].attach_offset, 2.0);
+        assert!(!messages.iter().any(|m| matches!(m, Message::Drop)));
+    }
+
+    #[test]
+    fn laser_bolts_break_reinforced_bricks() {
+        let mut state = test_state();
+        state.bricks.push(kind_at(glam::vec2(state.player.body.pos.x, 60.0), 1, BrickKind::Reinforced));
+        state.bricks.push(brick_at(glam::vec2(0.0, 76.0), 1));
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = glam::vec2(70.0, 30.0);
+        state.effects.push(Effect {
+            kind: PowerUpKind::Laser,
+            remaining: 10.0,
+        });
+
+        let mut system = MovementSystem::new(10.0);
+        system.fire = true;
+        let mut messages = Vec::new();
+        system.update(&mut state, 1.0 / 60.0, &mut messages);
+        assert_eq!(state.bolts.len(), 2);
+        assert!(messages.iter().any(|m| matches!(m, Message::LaserFired { .. })));
+
+        system.fire = false;
+        for _ in 0..90 {
+            system.update(&mut state, 1.0 / 60.0, &mut messages);
+        }
+        assert_eq!(state.bricks.len(), 1);
+        assert!(state.bolts.is_empty());
+    }
+
+    #[test]
+    fn paddle_cant_push_ball_through_wall() {
+        let mut state = test_state();
+        state.bricks.push(brick_at(glam::vec2(0.0, 70.0), 1));
+        state.player.body.pos.x = 60.0;
+        state.balls[0].fired = true;
+        state.balls[0].body.pos = glam::vec2(75.9, 1.0);
+        state.balls[0].vel = glam::vec2(2.0, -4.0);
+
+        let mut system = MovementSystem::new(PADDLE_SPEED);
+        system.dir = 1.0;
+        let mut messages = Vec::new();
+        for _ in 0..10 {
+            system.update(&mut state, 1.0 / 60.0, &mut messages);
+            let ball = &state.balls[0].body;
+            assert!(ball.pos.x + ball.size.x <= state.arena_size.x);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::Instant;
+
+/// Length of a simulation tick in seconds. Replays depend on every run using
+/// the same value.
+pub const SIM_STEP: f32 = 1.0 / 60.0;
+
+/// Turns real elapsed time into a whole number of fixed simulation steps so
+/// the game runs at the same speed regardless of the redraw rate.
+pub struct FixedTimestep {
+    step: f32,
+    max_steps: u32,
+    accumulator: f32,
+    last: Option<Instant>,
+}
+
+impl FixedTimestep {
+    /// `step` is the length of a simulation tick in seconds. `max_steps` caps
+    /// how many ticks a single frame can run to catch up after a hitch;
+    /// any time beyond that is dropped.
+    pub fn new(step: f32, max_steps: u32) -> Self {
+        Self {
+            step,
+            max_steps,
+            accumulator: 0.0,
+            last: None,
+        }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Measures the time since the last call and returns how many ticks to
+    /// run this frame. The first call after a reset never runs any.
+    pub fn tick(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = match self.last {
+            Some(last) => (now - last).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last = Some(now);
+        self.advance(elapsed)
+    }
+
+    /// Adds `elapsed` seconds to the accumulator and returns how many ticks
+    /// it now holds.
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+            if steps == self.max_steps {
+                // Too far behind to catch up, so just let it go. Only the
+                // part of a step is kept, or the next frame would run an
+                // extra tick straight away.
+                self.accumulator %= self.step;
+                break;
+            }
+        }
+        steps
+    }
+
+    /// How far between the last tick and the next one the current frame is,
+    /// in `[0, 1)`. Used to blend between the previous and current state.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+
+    /// Forgets any accumulated time, e.g. when coming back from a menu, so
+    /// the simulation doesn't try to make up for the time it was paused.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_a_tick_per_step_of_time() {
+        let mut timestep = FixedTimestep::new(0.25, 5);
+        assert_eq!(timestep.advance(0.1), 0);
+        assert_eq!(timestep.advance(0.2), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-5);
+        assert_eq!(timestep.advance(0.5), 2);
+    }
+
+    #[test]
+    fn long_stall_runs_max_steps_then_normal_pacing() {
+        let mut timestep = FixedTimestep::new(0.25, 5);
+        assert_eq!(timestep.advance(10.1), 5);
+        assert!((0.0..1.0).contains(&timestep.alpha()));
+        // Only the leftover 0.1 carried over, not a whole step.
+        assert_eq!(timestep.advance(0.1), 0);
+        assert_eq!(timestep.advance(0.1), 1);
+        assert!((0.0..1.0).contains(&timestep.alpha()));
+    }
+
+    #[test]
+    fn alpha_stays_below_one() {
+        let mut timestep = FixedTimestep::new(SIM_STEP, 5);
+        for frame in 0..1000 {
+            timestep.advance((frame % 7) as f32 * 0.006);
+            assert!((0.0..1.0).contains(&timestep.alpha()), "{}", timestep.alpha());
+        }
+        timestep.reset();
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
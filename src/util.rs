@@ -1,3 +1,4 @@
+use brick_breaker::input::MouseMode;
 use winit::window::Fullscreen;
 
 pub fn get_encoder(device: &wgpu::Device) -> wgpu::CommandEncoder {
@@ -19,4 +20,13 @@ pub fn set_fullscreen(fullscreen: bool, window: &winit::window::Window) {
     } else {
         window.set_fullscreen(None);
     }
-}
\ No newline at end of file
+}
+
+/// Hides the cursor while the mouse steers the paddle, and in relative mode
+/// keeps it from wandering off the window.
+pub fn capture_cursor(mode: MouseMode, window: &winit::window::Window) {
+    window.set_cursor_visible(mode == MouseMode::Off);
+    if let Err(e) = window.set_cursor_grab(mode == MouseMode::Relative) {
+        log::warn!("Couldn't grab the cursor: {}", e);
+    }
+}
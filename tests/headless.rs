@@ -0,0 +1,56 @@
+use brick_breaker::{
+    campaign::Campaign,
+    input::{Button, Controller},
+    state::State,
+    system::{Message, MovementSystem},
+};
+
+fn arena() -> State {
+    State::new(
+        glam::vec2(80.0, 74.0),
+        glam::vec2(16.0, 3.0),
+        glam::vec2(4.0, 4.0),
+        glam::vec2(8.0, 4.0),
+    )
+}
+
+#[test]
+fn campaign_levels_load() {
+    let campaign = Campaign::load(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/levels/campaign.ron")).unwrap();
+    let mut state = arena();
+    for level in &campaign.levels {
+        state.load_level(level).unwrap();
+        assert!(!state.bricks.is_empty(), "{} has no bricks", level.name);
+    }
+}
+
+#[test]
+fn fired_ball_leaves_the_paddle() {
+    let campaign = Campaign::load(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/levels/campaign.ron")).unwrap();
+    let mut state = arena();
+    state.new_game();
+    state.load_level(&campaign.levels[0]).unwrap();
+
+    let mut controller = Controller::new();
+    let mut movement = MovementSystem::new(10.0);
+    let mut messages = Vec::new();
+
+    // The first step after loading ignores fire so the menu press doesn't
+    // launch the ball.
+    movement.input(&controller);
+    movement.update(&mut state, 1.0 / 60.0, &mut messages);
+
+    controller.set_button(Button::Fire, true);
+    movement.input(&controller);
+    movement.update(&mut state, 1.0 / 60.0, &mut messages);
+    assert!(messages.iter().any(|m| matches!(m, Message::Fire { .. })));
+
+    let start = state.balls[0].body.pos;
+    controller.set_button(Button::Fire, false);
+    movement.input(&controller);
+    for _ in 0..30 {
+        movement.update(&mut state, 1.0 / 60.0, &mut messages);
+    }
+    assert!(state.balls[0].fired);
+    assert!(state.balls[0].body.pos.y > start.y);
+}